@@ -0,0 +1,45 @@
+use std::{env, fs, path::Path};
+
+// Lists everything in res/ so startup can check the install is complete, and
+// with the embed-assets feature compiles the files themselves into the
+// binary so it runs without them beside it.
+fn main() {
+    println!("cargo:rerun-if-changed=res");
+    println!("cargo:rerun-if-env-changed=CARGO_FEATURE_EMBED_ASSETS");
+
+    let mut files = vec![];
+    collect(Path::new("res"), &mut files);
+    files.sort();
+
+    let mut bundle = String::from("pub static MANIFEST: &[&str] = &[\n");
+    for file in &files {
+        bundle += &format!("    {:?},\n", file);
+    }
+    bundle += "];\n";
+
+    if env::var_os("CARGO_FEATURE_EMBED_ASSETS").is_some() {
+        let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+        bundle += "\npub static FILES: &[(&str, &[u8])] = &[\n";
+        for file in &files {
+            let path = Path::new(&manifest_dir).join(file);
+            bundle += &format!("    ({:?}, include_bytes!({:?})),\n", file, path);
+        }
+        bundle += "];\n";
+    }
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("bundle.rs");
+    fs::write(out, bundle).unwrap();
+}
+
+fn collect(dir: &Path, files: &mut Vec<String>) {
+    for entry in fs::read_dir(dir).unwrap().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect(&path, files);
+        } else {
+            // keys always use forward slashes, whatever the host
+            let key: Vec<_> = path.iter().map(|part| part.to_string_lossy()).collect();
+            files.push(key.join("/"));
+        }
+    }
+}
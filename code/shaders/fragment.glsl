@@ -2,26 +2,27 @@
 
 in vec2 v_tex_coords;
 in vec4 ourColor;
+in vec2 v_effect;
 
 out vec4 fragColor;
 
 uniform bool isTex;
 uniform sampler2D tex;
-uniform bool clipped;
-uniform vec2 start;
-uniform vec2 size;
+
+// Rotates the colour around the grey axis of the RGB cube.
+vec3 hueShift(vec3 color, float turns) {
+    float angle = turns * 6.2831853;
+    vec3 k = vec3(0.57735);
+    float c = cos(angle);
+    return color * c + cross(k, color) * sin(angle) + k * dot(k, color) * (1.0 - c);
+}
 
 void main() {
     if(isTex) {
-        fragColor = texture(tex, v_tex_coords);
-        if(clipped) {
-            vec2 min_coords = start / textureSize(tex, 0);
-            vec2 max_coords = (start + size) / textureSize(tex, 0);
-
-            if(v_tex_coords.x < min_coords.x || v_tex_coords.x > max_coords.x || 1 - v_tex_coords.y < min_coords.y || 1 - v_tex_coords.y > max_coords.y) {
-                discard;
-            }
-        }
+        fragColor = texture(tex, v_tex_coords) * ourColor;
     } else
         fragColor = ourColor;
+
+    fragColor.rgb = hueShift(fragColor.rgb, v_effect.y);
+    fragColor.rgb = mix(fragColor.rgb, vec3(1.0), v_effect.x);
 }
\ No newline at end of file
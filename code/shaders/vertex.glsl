@@ -3,14 +3,15 @@
 in vec2 position;
 in vec4 color; 
 in vec2 tex_coords;
+in vec2 effect;
 
 out vec4 ourColor;
 out vec2 v_tex_coords;
-
-uniform mat4 matrix;
+out vec2 v_effect;
 
 void main() {
     v_tex_coords = tex_coords;
     ourColor = color;
-    gl_Position = matrix * vec4(position, 0.0, 1.0);
+    v_effect = effect;
+    gl_Position = vec4(position, 0.0, 1.0);
 }
\ No newline at end of file
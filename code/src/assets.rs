@@ -0,0 +1,455 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs::{read, read_dir};
+use std::io::{self, Cursor};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+use glium::texture::{MipmapsOption, RawImage2d, SrgbTexture2d};
+use glium::Display;
+use image::RgbaImage;
+
+pub const RES_DIR: &str = "res";
+pub const ASSETS_ENV: &str = "UBILAND_ASSETS";
+
+// MANIFEST lists every file res/ held at build time, and with the
+// embed-assets feature FILES holds their contents.
+mod bundle {
+    include!(concat!(env!("OUT_DIR"), "/bundle.rs"));
+}
+
+#[derive(Debug)]
+pub enum AssetError {
+    Missing(PathBuf),
+    Unreadable(PathBuf, io::Error),
+    Corrupt(PathBuf, image::ImageError),
+    Malformed(PathBuf, String),
+    Upload(String),
+    Resized(PathBuf),
+    NoFrames,
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetError::Missing(path) => write!(f, "{} is missing", path.display()),
+            AssetError::Unreadable(path, e) => write!(f, "{} can't be read: {}", path.display(), e),
+            AssetError::Corrupt(path, e) => {
+                write!(f, "{} is not a valid png: {}", path.display(), e)
+            }
+            AssetError::Malformed(path, e) => write!(f, "{} is malformed: {}", path.display(), e),
+            AssetError::Upload(e) => write!(f, "unable to create a texture: {}", e),
+            AssetError::Resized(path) => {
+                write!(f, "{} changed size, restart to pick it up", path.display())
+            }
+            AssetError::NoFrames => write!(f, "an animation needs at least one frame"),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+// Where asset files are read from: a directory holding res/, or the copy
+// of res/ compiled into the binary.
+enum Source {
+    Dir(PathBuf),
+    #[cfg(feature = "embed-assets")]
+    Embedded,
+}
+
+static SOURCE: OnceLock<Source> = OnceLock::new();
+
+fn has_res(dir: &Path) -> bool {
+    dir.join(RES_DIR).is_dir()
+}
+
+// An explicit --assets flag or UBILAND_ASSETS must point at a directory
+// holding res/. Otherwise look beside the executable and in its parents
+// (which covers `cargo run`), then in the working directory, and finally
+// fall back to the embedded bundle when the binary was built with one.
+fn find_source(flag: Option<&Path>) -> Result<Source, String> {
+    let explicit = flag
+        .map(|dir| (dir.to_path_buf(), "--assets"))
+        .or_else(|| env::var_os(ASSETS_ENV).map(|dir| (PathBuf::from(dir), ASSETS_ENV)));
+    if let Some((dir, origin)) = explicit {
+        if has_res(&dir) {
+            return Ok(Source::Dir(dir));
+        }
+        return Err(format!(
+            "{} points at {}, which has no {} directory",
+            origin,
+            dir.display(),
+            RES_DIR
+        ));
+    }
+
+    let exe = env::current_exe().ok();
+    let beside_exe = exe.iter().flat_map(|exe| exe.ancestors().skip(1));
+    let cwd = env::current_dir().ok();
+    if let Some(dir) = beside_exe.chain(cwd.as_deref()).find(|dir| has_res(dir)) {
+        return Ok(Source::Dir(dir.to_path_buf()));
+    }
+
+    #[cfg(feature = "embed-assets")]
+    return Ok(Source::Embedded);
+
+    #[cfg(not(feature = "embed-assets"))]
+    Err(format!(
+        "could not find the {} directory; pass --assets DIR or set {}",
+        RES_DIR, ASSETS_ENV
+    ))
+}
+
+pub fn init(flag: Option<&Path>) -> Result<(), String> {
+    let source = find_source(flag)?;
+    SOURCE.get_or_init(|| source);
+    Ok(())
+}
+
+// The directory assets are read from, if they aren't embedded.
+pub fn root() -> Option<&'static Path> {
+    match source() {
+        Source::Dir(root) => Some(root),
+        #[cfg(feature = "embed-assets")]
+        Source::Embedded => None,
+    }
+}
+
+fn source() -> &'static Source {
+    SOURCE.get_or_init(|| Source::Dir(PathBuf::from(".")))
+}
+
+fn read_asset(key: &Path) -> Result<Vec<u8>, AssetError> {
+    let bytes = match source() {
+        Source::Dir(root) => read(root.join(key)),
+        #[cfg(feature = "embed-assets")]
+        Source::Embedded => bundle::FILES
+            .iter()
+            .find(|(name, _)| Path::new(name) == key)
+            .map(|(_, bytes)| bytes.to_vec())
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound)),
+    };
+    bytes.map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => AssetError::Missing(key.to_path_buf()),
+        _ => AssetError::Unreadable(key.to_path_buf(), e),
+    })
+}
+
+// Checks every file the game shipped with in one go, so a broken install
+// reports everything that's wrong with it instead of the first failure.
+// The images that decode are kept for packing the atlas, and the ones that
+// don't are remembered: if startup carries on regardless, they're drawn as
+// placeholders.
+pub fn validate() -> Vec<AssetError> {
+    let mut errors = vec![];
+    for name in bundle::MANIFEST
+        .iter()
+        .filter(|name| name.ends_with(".png"))
+    {
+        let key = key(Path::new(name));
+        match decode(&key) {
+            Ok(image) => {
+                DECODED.with(|decoded| decoded.borrow_mut().insert(key, image));
+            }
+            Err(e) => {
+                BROKEN.with(|broken| broken.borrow_mut().insert(key));
+                errors.push(e);
+            }
+        }
+    }
+    errors
+}
+
+// Every png under res/, as keys relative to the asset root.
+fn list_images() -> Vec<PathBuf> {
+    let mut keys = match source() {
+        Source::Dir(root) => {
+            let mut keys = vec![];
+            walk(root, Path::new(RES_DIR), &mut keys);
+            keys
+        }
+        #[cfg(feature = "embed-assets")]
+        Source::Embedded => bundle::FILES
+            .iter()
+            .map(|(name, _)| PathBuf::from(name))
+            .collect(),
+    };
+    keys.retain(|key| key.extension().is_some_and(|ext| ext == "png"));
+    keys
+}
+
+pub fn walk(root: &Path, dir: &Path, keys: &mut Vec<PathBuf>) {
+    let Ok(entries) = read_dir(root.join(dir)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let key = dir.join(entry.file_name());
+        if entry.path().is_dir() {
+            walk(root, &key, keys);
+        } else {
+            keys.push(key);
+        }
+    }
+}
+
+// Small enough for any GL 3 driver; everything in res/ fits on one page.
+const ATLAS_SIZE: u32 = 2048;
+
+// Each sprite is surrounded by a copy of its own edge pixels so linear
+// filtering never pulls in colours from its neighbours on the page.
+const EXTRUDE: u32 = 1;
+
+// A region of an atlas page. `uv` is the bottom-left corner and size in
+// texture coordinates, `width`/`height` the size in pixels.
+#[derive(Clone)]
+pub struct Sprite {
+    pub page: Rc<SrgbTexture2d>,
+    pub uv: [f32; 4],
+    pub width: f32,
+    pub height: f32,
+    // the texels it occupies on the page, extruded border included
+    bounds: glium::Rect,
+}
+
+thread_local! {
+    // GPU textures are tied to the display's context, which lives on the
+    // main thread, so the cache does too.
+    static SPRITES: RefCell<HashMap<PathBuf, Sprite>> = RefCell::new(HashMap::new());
+    static PLACEHOLDER: RefCell<Option<Sprite>> = const { RefCell::new(None) };
+    // images validate() decoded, until the atlas is packed from them
+    static DECODED: RefCell<HashMap<PathBuf, RgbaImage>> = RefCell::new(HashMap::new());
+    // images validate() couldn't load
+    static BROKEN: RefCell<HashSet<PathBuf>> = RefCell::new(HashSet::new());
+}
+
+// "./res/a.png" and "res/a.png" name the same sprite.
+fn key(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| *c != Component::CurDir)
+        .collect()
+}
+
+// The first request packs every image under res/ into atlas pages, so
+// all later ones are lookups. Anything outside res/ gets a page of its own.
+// Images that fail to decode are left out of the atlas. Those validate()
+// reported come back as the placeholder, since startup already chose to
+// carry on without them; any other failure is returned.
+pub fn sprite(path: &str, display: &Display) -> Result<Sprite, AssetError> {
+    let key = key(Path::new(path));
+    if BROKEN.with(|broken| broken.borrow().contains(&key)) {
+        return placeholder(display);
+    }
+
+    SPRITES.with(|sprites| {
+        let mut sprites = sprites.borrow_mut();
+        if sprites.is_empty() {
+            let mut decoded = DECODED.take();
+            let images: Vec<(PathBuf, RgbaImage)> = list_images()
+                .into_iter()
+                .filter_map(|key| {
+                    let image = match decoded.remove(&key) {
+                        Some(image) => image,
+                        None => decode(&key).ok()?,
+                    };
+                    Some((key, image))
+                })
+                .collect();
+            sprites.extend(pack(display, &images)?);
+        }
+
+        if !sprites.contains_key(&key) {
+            let image = decode(&key)?;
+            sprites.extend(pack(display, &[(key.clone(), image)])?);
+        }
+        Ok(sprites[&key].clone())
+    })
+}
+
+// The contents of a file that isn't an image, such as font metrics.
+pub fn text(path: &str) -> Result<String, AssetError> {
+    let key = key(Path::new(path));
+    let bytes = read_asset(&key)?;
+    String::from_utf8(bytes).map_err(|e| AssetError::Malformed(key, e.to_string()))
+}
+
+// Redraws a changed image into its spot on the atlas page, so every sprite
+// showing it updates at once. Images that were never loaded are ignored.
+// Packing is only done at startup, so an image must keep its size.
+pub fn reload(path: &Path) -> Result<(), AssetError> {
+    let key = key(path);
+    let Some(sprite) = SPRITES.with(|sprites| sprites.borrow().get(&key).cloned()) else {
+        return Ok(());
+    };
+
+    let image = decode(&key)?;
+    if image.dimensions() != (sprite.width as u32, sprite.height as u32) {
+        return Err(AssetError::Resized(key));
+    }
+
+    let bounds = sprite.bounds;
+    let mut extruded = RgbaImage::new(bounds.width, bounds.height);
+    blit_extruded(&mut extruded, &image, 0, 0);
+    let data =
+        RawImage2d::from_raw_rgba_reversed(&extruded.into_raw(), (bounds.width, bounds.height));
+    sprite.page.write(bounds, data);
+    Ok(())
+}
+
+// Loud enough that a missing texture can't be mistaken for real art.
+const PLACEHOLDER_SIZE: u32 = 32;
+const MAGENTA: image::Rgba<u8> = image::Rgba([255, 0, 255, 255]);
+const BLACK: image::Rgba<u8> = image::Rgba([0, 0, 0, 255]);
+
+// A magenta and black checkerboard, drawn in place of images that failed
+// validation when startup carries on anyway.
+fn placeholder(display: &Display) -> Result<Sprite, AssetError> {
+    if let Some(sprite) = PLACEHOLDER.with(|placeholder| placeholder.borrow().clone()) {
+        return Ok(sprite);
+    }
+
+    let half = PLACEHOLDER_SIZE / 2;
+    let image = RgbaImage::from_fn(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE, |x, y| {
+        if (x < half) == (y < half) {
+            MAGENTA
+        } else {
+            BLACK
+        }
+    });
+    let (_, sprite) = pack(display, &[(PathBuf::from("placeholder"), image)])?.remove(0);
+    PLACEHOLDER.with(|placeholder| *placeholder.borrow_mut() = Some(sprite.clone()));
+    Ok(sprite)
+}
+
+fn decode(key: &Path) -> Result<RgbaImage, AssetError> {
+    let bytes = read_asset(key)?;
+    let image = image::load(Cursor::new(bytes), image::ImageFormat::Png)
+        .map_err(|e| AssetError::Corrupt(key.to_path_buf(), e))?;
+    Ok(image.to_rgba8())
+}
+
+struct Placement {
+    key: PathBuf,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+// Shelf packing: tallest images first, left to right, starting a new shelf
+// when a row is full and a new page when the page is.
+fn pack(
+    display: &Display,
+    images: &[(PathBuf, RgbaImage)],
+) -> Result<Vec<(PathBuf, Sprite)>, AssetError> {
+    let mut order: Vec<&(PathBuf, RgbaImage)> = images.iter().collect();
+    order.sort_by(|a, b| b.1.height().cmp(&a.1.height()).then(a.0.cmp(&b.0)));
+
+    let mut sprites = vec![];
+    let mut page = RgbaImage::new(ATLAS_SIZE, ATLAS_SIZE);
+    let mut placements: Vec<Placement> = vec![];
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+
+    for (key, image) in order {
+        let width = image.width() + EXTRUDE * 2;
+        let height = image.height() + EXTRUDE * 2;
+
+        // an image too big for a page gets one of its own
+        if width > ATLAS_SIZE || height > ATLAS_SIZE {
+            let mut own = RgbaImage::new(width, height);
+            blit_extruded(&mut own, image, 0, 0);
+            let placement = Placement {
+                key: key.clone(),
+                x: EXTRUDE,
+                y: EXTRUDE,
+                width: image.width(),
+                height: image.height(),
+            };
+            sprites.extend(upload(display, &own, height, &[placement])?);
+            continue;
+        }
+
+        if x + width > ATLAS_SIZE {
+            x = 0;
+            y += shelf_height;
+            shelf_height = 0;
+        }
+        if y + height > ATLAS_SIZE && !placements.is_empty() {
+            sprites.extend(upload(display, &page, y + shelf_height, &placements)?);
+            page = RgbaImage::new(ATLAS_SIZE, ATLAS_SIZE);
+            placements.clear();
+            (x, y, shelf_height) = (0, 0, 0);
+        }
+        blit_extruded(&mut page, image, x, y);
+        placements.push(Placement {
+            key: key.clone(),
+            x: x + EXTRUDE,
+            y: y + EXTRUDE,
+            width: image.width(),
+            height: image.height(),
+        });
+
+        x += width;
+        shelf_height = shelf_height.max(height);
+    }
+
+    if !placements.is_empty() {
+        sprites.extend(upload(display, &page, y + shelf_height, &placements)?);
+    }
+    Ok(sprites)
+}
+
+fn blit_extruded(page: &mut RgbaImage, image: &RgbaImage, x: u32, y: u32) {
+    let (w, h) = image.dimensions();
+    for dy in 0..h + EXTRUDE * 2 {
+        for dx in 0..w + EXTRUDE * 2 {
+            let sx = dx.saturating_sub(EXTRUDE).min(w - 1);
+            let sy = dy.saturating_sub(EXTRUDE).min(h - 1);
+            page.put_pixel(x + dx, y + dy, *image.get_pixel(sx, sy));
+        }
+    }
+}
+
+// Pages are cropped to the rows actually used before upload.
+fn upload(
+    display: &Display,
+    page: &RgbaImage,
+    used_height: u32,
+    placements: &[Placement],
+) -> Result<Vec<(PathBuf, Sprite)>, AssetError> {
+    let page = image::imageops::crop_imm(page, 0, 0, page.width(), used_height).to_image();
+    let (page_width, page_height) = page.dimensions();
+
+    let image = RawImage2d::from_raw_rgba_reversed(&page.into_raw(), (page_width, page_height));
+    let texture = SrgbTexture2d::with_mipmaps(display, image, MipmapsOption::NoMipmap)
+        .map_err(|e| AssetError::Upload(e.to_string()))?;
+    let texture = Rc::new(texture);
+
+    // the image is flipped on upload, so v runs up from the page's bottom row
+    let sprites = placements
+        .iter()
+        .map(|p| {
+            let sprite = Sprite {
+                page: texture.clone(),
+                uv: [
+                    p.x as f32 / page_width as f32,
+                    (page_height - p.y - p.height) as f32 / page_height as f32,
+                    p.width as f32 / page_width as f32,
+                    p.height as f32 / page_height as f32,
+                ],
+                width: p.width as f32,
+                height: p.height as f32,
+                bounds: glium::Rect {
+                    left: p.x - EXTRUDE,
+                    bottom: page_height - p.y - p.height - EXTRUDE,
+                    width: p.width + EXTRUDE * 2,
+                    height: p.height + EXTRUDE * 2,
+                },
+            };
+            (p.key.clone(), sprite)
+        })
+        .collect();
+    Ok(sprites)
+}
@@ -1,97 +1,185 @@
-use glium::{Display, Frame, Program};
-use rand::rngs::ThreadRng;
-use rand::Rng;
+use glium::Display;
 
-use crate::shape::{Direction, Rectangle, BOTTOM, LEFT, RIGHT, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::assets::AssetError;
+use crate::batch::SpriteBatch;
+use crate::rng::{roll, BACKGROUND_STREAM};
+use crate::shape::{BOTTOM, LEFT, RIGHT, SCREEN_WIDTH};
+use crate::sky::Sky;
 use crate::texture::{AnimatedTexture, Texture, Transform};
 
-pub struct Background {
-    background_clouds: [Texture; 3],
-    birds: [AnimatedTexture; 5],
-    background_gradient: Rectangle,
-    rand: ThreadRng,
+// One horizontally tiling strip of scenery.
+struct LayerDef {
+    // images a tile can show and how likely each is
+    variants: &'static [(&'static str, u32)],
+    // share of the camera's movement the layer follows: 0 is pinned to
+    // the sky, 1 moves with the world
+    depth: f32,
+    // movement of its own, in pixels per second
+    drift: f32,
+    // height of the tiles' bottom edge above the bottom of the screen
+    bottom: f32,
+    // gap between neighbouring tiles, negative to overlap them
+    spacing: f32,
 }
 
-impl Background {
-    pub fn new(display: &Display) -> Self {
-        let mut big_cloud1 = Texture::new("./res/background/big_cloud.png", display);
-        big_cloud1.set_position(0., BOTTOM + big_cloud1.height / 2.);
-
-        let mut big_cloud2 = Texture::new("./res/background/big_cloud.png", display);
-        big_cloud2.set_position(SCREEN_WIDTH - 3., BOTTOM + big_cloud2.height / 2.);
-
-        let mut big_cloud3 = Texture::new("./res/background/rainbow.png", display);
-        big_cloud3.set_position(SCREEN_WIDTH - 3., BOTTOM + big_cloud3.height / 2.);
-
-        let clouds = [big_cloud1, big_cloud2, big_cloud3];
-
-        let mut rect = Rectangle::new(display, SCREEN_WIDTH, SCREEN_HEIGHT);
-        rect.set_color(display, [0.8, 0.5, 0.3, 1.0]);
-        rect.set_gradient(
-            display,
-            [1.0, 0.45, 1.0, 0.8],
-            [0.3, 0.3, 1.0, 0.8],
-            Direction::Vertical,
-        );
-
-        let mut array = [
-            AnimatedTexture::new(display, vec!["./res/background/bird1.png", "./res/background/bird2.png"], 0.3, 2),
-            AnimatedTexture::new(display, vec!["./res/background/bird1.png", "./res/background/bird2.png"], 0.3, 2),
-            AnimatedTexture::new(display, vec!["./res/background/bird1.png", "./res/background/bird2.png"], 0.3, 2),
-            AnimatedTexture::new(display, vec!["./res/background/bird1.png", "./res/background/bird2.png"], 0.3, 2),
-            AnimatedTexture::new(display, vec!["./res/background/bird1.png", "./res/background/bird2.png"], 0.3, 2),
-        ];
-
-        array[0].set_position(0.0, 0.0);
-        array[1].set_position(100.0, -250.0);
-        array[2].set_position(-300.0, -100.0);
-        array[3].set_position(200.0, 170.0);
-        array[4].set_position(-150.0, 100.0);
-
-        Background {
-            background_clouds: clouds,
-            birds: array,
-            background_gradient: rect,
-            rand: rand::thread_rng(),
-        }
+// Furthest first.
+const LAYERS: &[LayerDef] = &[
+    LayerDef {
+        variants: &[("./res/background/small_cloud.png", 1)],
+        depth: 0.05,
+        drift: -8.0,
+        bottom: 420.0,
+        spacing: 420.0,
+    },
+    LayerDef {
+        variants: &[("./res/background/medium_cloud.png", 1)],
+        depth: 0.1,
+        drift: -12.0,
+        bottom: 300.0,
+        spacing: 380.0,
+    },
+    LayerDef {
+        variants: &[
+            ("./res/background/big_cloud.png", 3),
+            ("./res/background/rainbow.png", 1),
+        ],
+        depth: 0.25,
+        drift: -4.0,
+        bottom: 0.0,
+        spacing: -3.0,
+    },
+];
+
+const BIRD_SPEED: f32 = 20.0;
+const BIRD_DEPTH: f32 = 0.4;
+const BIRD_POSITIONS: [(f32, f32); 5] = [
+    (0.0, 0.0),
+    (100.0, -250.0),
+    (-300.0, -100.0),
+    (200.0, 170.0),
+    (-150.0, 100.0),
+];
+
+struct Layer {
+    def: &'static LayerDef,
+    variants: Vec<Texture>,
+    tile_width: f32,
+    drifted: f32,
+}
+
+impl Layer {
+    fn new(display: &Display, def: &'static LayerDef) -> Result<Self, AssetError> {
+        let variants = def
+            .variants
+            .iter()
+            .map(|(path, _)| Texture::new(path, display))
+            .collect::<Result<Vec<Texture>, AssetError>>()?;
+        let tile_width = variants[0].width + def.spacing;
+
+        Ok(Self {
+            def,
+            variants,
+            tile_width,
+            drifted: 0.0,
+        })
     }
 
-    pub fn update(&mut self, dt: f32) {
-        if self.background_clouds[0].x + self.background_clouds[0].width / 2. < LEFT {
-            self.background_clouds[0].set_x(SCREEN_WIDTH - 3.);
-        }
-        if self.background_clouds[1].x + self.background_clouds[1].width / 2. < LEFT {
-            let x: u8 = self.rand.gen_range(0..4);
-            if x == 3 {
-                self.background_clouds[2].set_x(SCREEN_WIDTH - 3.);
-            } else {
-                self.background_clouds[1].set_x(SCREEN_WIDTH - 3.);
+    // Which variant tile `index` shows depends only on the seed, so it
+    // looks the same every time it comes around.
+    fn variant(&self, seed: u64, layer: usize, index: i64) -> usize {
+        let total: u32 = self.def.variants.iter().map(|(_, weight)| weight).sum();
+        let mut pick = (roll(seed, BACKGROUND_STREAM + layer as u64, index) % total as u64) as u32;
+        for (i, (_, weight)) in self.def.variants.iter().enumerate() {
+            if pick < *weight {
+                return i;
             }
+            pick -= weight;
         }
+        0
+    }
 
-        for i in 0..3 {
-            self.background_clouds[i].translate(-0.015, 0.0);
+    fn draw(&mut self, batch: &mut SpriteBatch, seed: u64, layer: usize, camera_x: f32) {
+        let scroll = camera_x * self.def.depth - self.drifted;
+        let half = self.variants[0].width / 2.0;
+        let first = ((scroll + LEFT - half) / self.tile_width).floor() as i64;
+        let last = ((scroll + RIGHT + half) / self.tile_width).ceil() as i64;
+
+        for index in first..=last {
+            let variant = self.variant(seed, layer, index);
+            let texture = &mut self.variants[variant];
+            let y = BOTTOM + self.def.bottom + texture.height / 2.0;
+            texture.set_position(index as f32 * self.tile_width - scroll, y);
+            texture.draw(batch);
         }
+    }
+}
 
-        for i in 0..5 {
-            self.birds[i].update(dt);
-            self.birds[i].translate(0.012, 0.0);
+pub struct Background {
+    layers: Vec<Layer>,
+    birds: Vec<AnimatedTexture>,
+    bird_x: Vec<f32>,
+    sky: Sky,
+    seed: u64,
+}
 
-            if self.birds[i].x >= RIGHT + 10. {
-                self.birds[i].set_x(LEFT - 10.);
-            }
+impl Background {
+    pub fn new(display: &Display, seed: u64, weather: bool) -> Result<Self, AssetError> {
+        let mut birds = vec![];
+        for (_, y) in BIRD_POSITIONS {
+            let mut bird = AnimatedTexture::new(
+                display,
+                &["./res/background/bird1.png", "./res/background/bird2.png"],
+                0.3,
+            )?;
+            bird.set_y(y);
+            birds.push(bird);
         }
+
+        Ok(Background {
+            layers: LAYERS
+                .iter()
+                .map(|def| Layer::new(display, def))
+                .collect::<Result<_, _>>()?,
+            birds,
+            bird_x: BIRD_POSITIONS.iter().map(|(x, _)| *x).collect(),
+            sky: Sky::new(seed, weather),
+            seed,
+        })
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.sky.reseed(seed);
     }
 
-    pub fn draw(&mut self, target: &mut Frame, program: &Program) {
-        self.background_gradient.draw(target, program);
+    pub fn update(&mut self, dt: f32, distance: u32) {
+        self.sky.update(dt, distance);
 
-        for i in 0..3 {
-            self.background_clouds[i].draw(target, program);
+        for layer in self.layers.iter_mut() {
+            layer.drifted += layer.def.drift * dt;
         }
 
-        for i in 0..5 {
-            self.birds[i].draw(target, program);
+        for (bird, x) in self.birds.iter_mut().zip(self.bird_x.iter_mut()) {
+            bird.update(dt);
+            *x += BIRD_SPEED * dt;
         }
     }
+
+    pub fn draw(&mut self, batch: &mut SpriteBatch, camera_x: f32) {
+        self.sky.draw(batch);
+
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            layer.draw(batch, self.seed, i, camera_x);
+        }
+
+        // birds wrap around a band a little wider than the screen
+        let band = SCREEN_WIDTH + 20.0;
+        for (bird, x) in self.birds.iter_mut().zip(self.bird_x.iter()) {
+            let screen_x = (x - camera_x * BIRD_DEPTH - (LEFT - 10.0)).rem_euclid(band);
+            bird.set_x(LEFT - 10.0 + screen_x);
+            bird.draw(batch);
+        }
+
+        self.sky.draw_weather(batch);
+    }
 }
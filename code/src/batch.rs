@@ -0,0 +1,162 @@
+use std::rc::Rc;
+
+use glium::index::PrimitiveType;
+use glium::texture::SrgbTexture2d;
+use glium::{uniform, Display, IndexBuffer, Program, Rect, Surface, VertexBuffer};
+
+use crate::shape::{Vertex, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+pub const BACKGROUND_LAYER: u8 = 0;
+pub const WORLD_LAYER: u8 = 1;
+pub const HUD_LAYER: u8 = 2;
+
+// Order inside a layer, back to front. Anything not given a depth is
+// drawn at 0.
+pub const PLATFORM_DEPTH: i32 = 1;
+pub const PICKUP_DEPTH: i32 = 2;
+pub const ENEMY_DEPTH: i32 = 3;
+pub const GHOST_DEPTH: i32 = 4;
+pub const PLAYER_DEPTH: i32 = 5;
+pub const EFFECT_DEPTH: i32 = 6;
+// Text and numbers on menus and the HUD, over the panels they sit on.
+pub const LABEL_DEPTH: i32 = 7;
+
+const INITIAL_QUADS: usize = 256;
+
+struct Quad {
+    layer: u8,
+    depth: i32,
+    page: Option<Rc<SrgbTexture2d>>,
+    vertices: [Vertex; 4],
+}
+
+impl Quad {
+    // Which texture the quad is drawn with; untextured quads are all 0.
+    fn page_key(&self) -> usize {
+        self.page
+            .as_ref()
+            .map_or(0, |page| Rc::as_ptr(page) as usize)
+    }
+}
+
+// Quads are collected over a frame with their vertices already in screen
+// space, then sorted by layer and depth and drawn with one call per run of
+// the same texture. The sort is stable, so quads at the same depth keep the
+// order they were pushed in whichever page their sprites were packed on.
+pub struct SpriteBatch {
+    display: Display,
+    layer: u8,
+    offset: [f32; 2],
+    quads: Vec<Quad>,
+    vertex_buffer: VertexBuffer<Vertex>,
+    index_buffer: IndexBuffer<u32>,
+}
+
+fn buffers(display: &Display, quads: usize) -> (VertexBuffer<Vertex>, IndexBuffer<u32>) {
+    let indices: Vec<u32> = (0..quads as u32)
+        .flat_map(|quad| [0, 1, 2, 2, 1, 3].map(|i| quad * 4 + i))
+        .collect();
+
+    (
+        VertexBuffer::empty_dynamic(display, quads * 4).unwrap(),
+        IndexBuffer::new(display, PrimitiveType::TrianglesList, &indices).unwrap(),
+    )
+}
+
+impl SpriteBatch {
+    pub fn new(display: &Display) -> Self {
+        let (vertex_buffer, index_buffer) = buffers(display, INITIAL_QUADS);
+        Self {
+            display: display.clone(),
+            layer: WORLD_LAYER,
+            offset: [0.0, 0.0],
+            quads: vec![],
+            vertex_buffer,
+            index_buffer,
+        }
+    }
+
+    pub fn set_layer(&mut self, layer: u8) {
+        self.layer = layer;
+    }
+
+    // Shifts everything pushed afterwards by (x, y) pixels; this is how a
+    // camera moves sprites placed in world coordinates.
+    pub fn set_offset(&mut self, x: f32, y: f32) {
+        self.offset = [x * 2.0 / SCREEN_WIDTH, y * 2.0 / SCREEN_HEIGHT];
+    }
+
+    pub fn push(&mut self, page: Option<&Rc<SrgbTexture2d>>, depth: i32, vertices: [Vertex; 4]) {
+        let [dx, dy] = self.offset;
+        self.quads.push(Quad {
+            layer: self.layer,
+            depth,
+            page: page.cloned(),
+            vertices: vertices.map(|v| Vertex {
+                position: [v.position[0] + dx, v.position[1] + dy],
+                ..v
+            }),
+        });
+    }
+
+    pub fn flush<S: Surface>(&mut self, target: &mut S, program: &Program, viewport: Rect) {
+        if self.quads.is_empty() {
+            return;
+        }
+        self.quads.sort_by_key(|quad| (quad.layer, quad.depth));
+
+        let capacity = self.vertex_buffer.len() / 4;
+        if self.quads.len() > capacity {
+            let quads = self.quads.len().next_power_of_two();
+            (self.vertex_buffer, self.index_buffer) = buffers(&self.display, quads);
+        }
+
+        let vertices: Vec<Vertex> = self.quads.iter().flat_map(|quad| quad.vertices).collect();
+        self.vertex_buffer
+            .slice(0..vertices.len())
+            .unwrap()
+            .write(&vertices);
+
+        let params = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            viewport: Some(viewport),
+            ..Default::default()
+        };
+
+        let mut start = 0;
+        while start < self.quads.len() {
+            let key = self.quads[start].page_key();
+            let end = start
+                + self.quads[start..]
+                    .iter()
+                    .take_while(|quad| {
+                        quad.page_key() == key && quad.layer == self.quads[start].layer
+                    })
+                    .count();
+
+            let indices = self.index_buffer.slice(start * 6..end * 6).unwrap();
+            match &self.quads[start].page {
+                Some(page) => {
+                    let uniforms = uniform! {
+                        isTex: true,
+                        tex: &**page,
+                    };
+                    target
+                        .draw(&self.vertex_buffer, indices, program, &uniforms, &params)
+                        .unwrap();
+                }
+                None => {
+                    let uniforms = uniform! {
+                        isTex: false,
+                    };
+                    target
+                        .draw(&self.vertex_buffer, indices, program, &uniforms, &params)
+                        .unwrap();
+                }
+            }
+            start = end;
+        }
+
+        self.quads.clear();
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::imageops::{self, FilterType};
+use image::{Delay, Frame, ImageOutputFormat, ImageResult, RgbaImage};
+
+use crate::renderer::Renderer;
+
+pub const CAPTURE_DIR: &str = "captures";
+// Recording a clip reads the canvas back from the GPU every frame it keeps,
+// which stalls rendering, so clips are off unless asked for. At the longest
+// the frames held in memory come to a few dozen megabytes, since they're
+// recorded at a lower rate and half the size of the canvas.
+pub const MAX_GIF_SECONDS: f32 = 10.0;
+const GIF_FPS: u32 = 15;
+const GIF_SHRINK: u32 = 2;
+// 1 is the best quality and slowest, 30 the worst and fastest.
+const GIF_SPEED: i32 = 10;
+
+// Saves screenshots and, from a rolling buffer of the last few seconds,
+// animated clips. Encoding happens on a thread of its own so the game
+// doesn't stall while a file is written.
+pub struct Capture {
+    dir: PathBuf,
+    frames: VecDeque<RgbaImage>,
+    max_frames: usize,
+    timer: f32,
+}
+
+// Seconds since the epoch as a UTC date and time, YYYYMMDD-HHMMSS.
+fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+
+    // Howard Hinnant's civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+// A new file in `dir`, numbered when two captures land in the same second.
+// It's created before the capture is handed to a thread to write, so a
+// second capture can't pick the same name while the first is still encoding.
+fn create_capture(dir: &Path, extension: &str) -> io::Result<(PathBuf, File)> {
+    let stamp = timestamp();
+    let mut path = dir.join(format!("ubiland-{}.{}", stamp, extension));
+    let mut n = 2;
+    loop {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                path = dir.join(format!("ubiland-{}-{}.{}", stamp, n, extension));
+                n += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn write_gif(file: BufWriter<File>, frames: Vec<RgbaImage>) -> ImageResult<()> {
+    let mut encoder = GifEncoder::new_with_speed(file, GIF_SPEED);
+    encoder.set_repeat(Repeat::Infinite)?;
+    let delay = Delay::from_numer_denom_ms(1000, GIF_FPS);
+    encoder.encode_frames(
+        frames
+            .into_iter()
+            .map(|frame| Frame::from_parts(frame, 0, 0, delay)),
+    )
+}
+
+impl Capture {
+    // `seconds` of 0 turns clip recording off.
+    pub fn new(dir: PathBuf, seconds: f32) -> Self {
+        let max_frames = (seconds.clamp(0.0, MAX_GIF_SECONDS) * GIF_FPS as f32).round() as usize;
+        Self {
+            dir,
+            frames: VecDeque::with_capacity(max_frames),
+            max_frames,
+            timer: 0.0,
+        }
+    }
+
+    fn write(
+        &self,
+        extension: &str,
+        save: impl FnOnce(BufWriter<File>) -> ImageResult<()> + Send + 'static,
+    ) {
+        let created =
+            fs::create_dir_all(&self.dir).and_then(|_| create_capture(&self.dir, extension));
+        let (path, file) = match created {
+            Ok(created) => created,
+            Err(e) => {
+                eprintln!("Unable to save to {}: {}", self.dir.display(), e);
+                return;
+            }
+        };
+        thread::spawn(move || match save(BufWriter::new(file)) {
+            Ok(()) => println!("Saved {}", path.display()),
+            Err(e) => eprintln!("Unable to save {}: {}", path.display(), e),
+        });
+    }
+
+    // Keeps a frame every 1/GIF_FPS seconds, dropping the oldest once the
+    // buffer holds `seconds` worth.
+    pub fn update(&mut self, renderer: &Renderer, dt: f32) {
+        if self.max_frames == 0 {
+            return;
+        }
+        self.timer += dt;
+        if self.timer < 1.0 / GIF_FPS as f32 {
+            return;
+        }
+        self.timer %= 1.0 / GIF_FPS as f32;
+
+        let frame = renderer.frame();
+        let (width, height) = frame.dimensions();
+        let frame = imageops::resize(
+            &frame,
+            width / GIF_SHRINK,
+            height / GIF_SHRINK,
+            FilterType::Nearest,
+        );
+        if self.frames.len() == self.max_frames {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    pub fn screenshot(&self, renderer: &Renderer) {
+        let frame = renderer.frame();
+        self.write("png", move |mut file| {
+            frame.write_to(&mut file, ImageOutputFormat::Png)
+        });
+    }
+
+    pub fn save_clip(&self) {
+        if self.max_frames == 0 {
+            eprintln!("Clips are off, start with --gif-seconds N to record them");
+            return;
+        }
+        let frames: Vec<RgbaImage> = self.frames.iter().cloned().collect();
+        self.write("gif", move |file| write_gif(file, frames));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_in_the_same_second_get_their_own_files() {
+        let dir = std::env::temp_dir().join(format!("ubiland-captures-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let paths: Vec<PathBuf> = (0..3)
+            .map(|_| create_capture(&dir, "png").unwrap().0)
+            .collect();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_ne!(paths[0], paths[1]);
+        assert_ne!(paths[1], paths[2]);
+        assert_ne!(paths[0], paths[2]);
+    }
+}
@@ -1,33 +1,213 @@
-use crate::texture::{AnimatedTexture, Texture, Rect};
+// An axis-aligned box, positioned by its centre like everything else in the
+// world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+// Which side of a box another one is touching.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Aabb {
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    pub fn left(&self) -> f32 {
+        self.x - self.w / 2.0
+    }
+
+    pub fn right(&self) -> f32 {
+        self.x + self.w / 2.0
+    }
+
+    pub fn top(&self) -> f32 {
+        self.y + self.h / 2.0
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y - self.h / 2.0
+    }
+
+    // Boxes that only share an edge don't count as overlapping.
+    pub fn overlaps_x(&self, other: &Aabb) -> bool {
+        self.right() > other.left() && self.left() < other.right()
+    }
+
+    pub fn overlaps_y(&self, other: &Aabb) -> bool {
+        self.top() > other.bottom() && self.bottom() < other.top()
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.overlaps_x(other) && self.overlaps_y(other)
+    }
+
+    // The shortest move that takes this box back out of `other`, along
+    // whichever axis it went in the least. Ties go to the vertical, so
+    // landing square on a corner counts as landing.
+    pub fn penetration(&self, other: &Aabb) -> Option<[f32; 2]> {
+        if !self.intersects(other) {
+            return None;
+        }
+
+        let dx = if self.x < other.x {
+            other.left() - self.right()
+        } else {
+            other.right() - self.left()
+        };
+        let dy = if self.y < other.y {
+            other.bottom() - self.top()
+        } else {
+            other.top() - self.bottom()
+        };
 
-pub fn overlap_x(a: Rect, b: Rect) -> bool {
-    if a.x + a.w / 2.0 <= b.x - b.w / 2.0 {
-        return false;
+        if dy.abs() <= dx.abs() {
+            Some([0.0, dy])
+        } else {
+            Some([dx, 0.0])
+        }
     }
 
-    if a.x - a.w / 2.0 >= b.x + b.w / 2.0 {
-        return false;
+    // The side of `other` this box ran into, if they intersect.
+    pub fn contact(&self, other: &Aabb) -> Option<Side> {
+        self.penetration(other).map(|[dx, dy]| {
+            if dx < 0.0 {
+                Side::Left
+            } else if dx > 0.0 {
+                Side::Right
+            } else if dy > 0.0 {
+                Side::Top
+            } else {
+                Side::Bottom
+            }
+        })
     }
+}
 
-    true
+// Where an entity can be hit, relative to its position. Usually a little
+// smaller than the sprite, so brushing a transparent corner doesn't count.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hitbox {
+    pub offset: [f32; 2],
+    pub w: f32,
+    pub h: f32,
 }
 
-pub fn intersect(a: &Texture, b: &AnimatedTexture) -> bool {
-    if a.y - a.height / 2.0 >= b.y + b.height / 2.0 {
-        return false;
+impl Hitbox {
+    pub const fn new(w: f32, h: f32) -> Self {
+        Self {
+            offset: [0.0, 0.0],
+            w,
+            h,
+        }
+    }
+
+    pub const fn with_offset(mut self, x: f32, y: f32) -> Self {
+        self.offset = [x, y];
+        self
     }
 
-    if a.y + a.height / 2.0 <= b.y - b.height / 2.0 {
-        return false;
+    pub fn at(&self, x: f32, y: f32) -> Aabb {
+        Aabb::new(x + self.offset[0], y + self.offset[1], self.w, self.h)
     }
+}
+
+// Anything that takes up space in the world. The tests work between any two
+// kinds of entity.
+pub trait Collider {
+    fn aabb(&self) -> Aabb;
 
-    if a.x + a.width / 2.0 <= b.x - b.width / 2.0 {
-        return false;
+    fn intersects(&self, other: &impl Collider) -> bool {
+        self.aabb().intersects(&other.aabb())
     }
 
-    if a.x - a.width / 2.0 >= b.x + b.width / 2.0 {
-        return false;
+    fn penetration(&self, other: &impl Collider) -> Option<[f32; 2]> {
+        self.aabb().penetration(&other.aabb())
     }
 
-    true
+    fn contact(&self, other: &impl Collider) -> Option<Side> {
+        self.aabb().contact(&other.aabb())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 20x20 around the origin, hit by 10x10 boxes.
+    const OTHER: Aabb = Aabb {
+        x: 0.0,
+        y: 0.0,
+        w: 20.0,
+        h: 20.0,
+    };
+
+    fn square(x: f32, y: f32) -> Aabb {
+        Aabb::new(x, y, 10.0, 10.0)
+    }
+
+    #[test]
+    fn each_side_pushes_back_out_the_way_it_came() {
+        // 3 deep along one axis and 13 along the other
+        let cases = [
+            (square(-12.0, 2.0), [-3.0, 0.0], Side::Left),
+            (square(12.0, 2.0), [3.0, 0.0], Side::Right),
+            (square(2.0, 12.0), [0.0, 3.0], Side::Top),
+            (square(2.0, -12.0), [0.0, -3.0], Side::Bottom),
+        ];
+        for (i, (square, push, side)) in cases.into_iter().enumerate() {
+            assert_eq!(square.penetration(&OTHER), Some(push), "case {}", i);
+            assert_eq!(square.contact(&OTHER), Some(side), "case {}", i);
+        }
+    }
+
+    #[test]
+    fn the_shallower_axis_wins() {
+        let deep = Aabb::new(0.0, 14.0, 10.0, 10.0);
+        assert_eq!(deep.penetration(&OTHER), Some([0.0, 1.0]));
+        let wide = Aabb::new(-14.0, 0.0, 10.0, 10.0);
+        assert_eq!(wide.penetration(&OTHER), Some([-1.0, 0.0]));
+    }
+
+    #[test]
+    fn touching_edges_dont_collide() {
+        for square in [
+            square(15.0, 0.0),
+            square(-15.0, 0.0),
+            square(0.0, 15.0),
+            square(0.0, -15.0),
+            square(15.0, 15.0),
+            square(-15.0, -15.0),
+        ] {
+            assert!(!square.intersects(&OTHER), "{:?}", square);
+            assert_eq!(square.penetration(&OTHER), None);
+            assert_eq!(square.contact(&OTHER), None);
+        }
+        assert!(square(14.9, 0.0).intersects(&OTHER));
+    }
+
+    // Coming in square on a corner counts as landing on or hitting the
+    // underside, never as a wall.
+    #[test]
+    fn ties_go_vertical() {
+        assert_eq!(square(12.0, 12.0).penetration(&OTHER), Some([0.0, 3.0]));
+        assert_eq!(square(-12.0, 12.0).contact(&OTHER), Some(Side::Top));
+        assert_eq!(square(12.0, -12.0).contact(&OTHER), Some(Side::Bottom));
+        assert_eq!(square(-12.0, -12.0).contact(&OTHER), Some(Side::Bottom));
+    }
+
+    #[test]
+    fn hitboxes_follow_their_owner() {
+        let hitbox = Hitbox::new(10.0, 20.0).with_offset(1.0, -2.0);
+        assert_eq!(hitbox.at(5.0, 5.0), Aabb::new(6.0, 3.0, 10.0, 20.0));
+    }
 }
@@ -1,99 +1,122 @@
-use glium::{glutin::event::VirtualKeyCode, Display, Frame, Program};
-use rand::{rngs::ThreadRng, Rng};
+use glium::{glutin::event::VirtualKeyCode, Display};
 
 use crate::{
-    collision::overlap_x,
-    enemy::{Enemy, Species, SPAWN_DELAY},
+    assets::AssetError,
+    batch::{SpriteBatch, HUD_LAYER},
+    enemy::Enemy,
+    ghost::Ghost,
+    gui::Topbar,
     input_mgr::InputManager,
-    platform::{Platform, Size, Type},
+    juice::Juice,
+    particles::Particles,
+    platform::Platform,
     player::Player,
-    shape::{BOTTOM, LEFT, RIGHT, SCREEN_WIDTH, TOP},
-    texture::{Rect, Texture, Transform}, gui::Topbar,
+    replay::{Playback, Recorder, Replay},
+    shape::SCREEN_WIDTH,
+    sim::{interpolate, Scores, World},
+    texture::{Texture, Transform},
 };
 
+const CONTROLS_POSITIONS: [(f32, f32); 2] = [(-210.0, 160.0), (510.0, 160.0)];
+
 pub struct Level {
+    world: World,
+    previous: World,
     player: Player,
     platforms: Vec<Platform>,
     enemies: Vec<Enemy>,
+    particles: Particles,
+    juice: Juice,
     controls: Vec<Texture>,
+    controls_bob: f32,
     elapsed_time: f32,
-    spawn_time: f32,
-    game_over_delay: f32,
-    rand: ThreadRng,
     topbar: Topbar,
     paused: bool,
+    recorder: Recorder,
+    playback: Option<Playback>,
+    ghost: Option<Ghost>,
 }
 
 impl Level {
-    pub fn new(display: &Display) -> Self {
-        let p = Player::new(display);
-
-        let mut platforms: Vec<Platform> = vec![];
+    pub fn new(display: &Display, seed: u64, effects: bool) -> Result<Self, AssetError> {
+        let world = World::new(seed);
 
-        let mut starting_platform = Platform::new(display, Size::XLarge);
-        starting_platform.set_position(LEFT + 100.0, -50.0);
-        platforms.push(starting_platform);
-
-        for i in 0..3 {
-            platforms.push(Platform::new(display, Size::from_u32(i % 3)));
-        }
-
-        platforms[3].set_position(510.0, -100.0);
-        platforms[2].set_position(800.0, -150.0);
-        platforms[1].set_position(1060.0, 50.0);
+        let platforms = world
+            .platforms
+            .iter()
+            .map(|state| Platform::new(display, state))
+            .collect::<Result<_, _>>()?;
 
         let mut controls: Vec<Texture> = vec![];
 
-        controls.push(Texture::new("./res/gui/controls1.png", display));
+        controls.push(Texture::new("./res/gui/controls1.png", display)?);
         controls[0].scale(0.8);
-        controls[0].set_position(-210.0, 160.0);
 
-        controls.push(Texture::new("./res/gui/controls2.png", display));
+        controls.push(Texture::new("./res/gui/controls2.png", display)?);
         controls[1].scale(0.8);
-        controls[1].set_position(510.0, 160.0);
 
-        Level {
-            player: p,
-            platforms: platforms,
+        Ok(Level {
+            previous: world.clone(),
+            player: Player::new(display)?,
+            platforms,
             enemies: vec![],
-            controls: controls,
+            particles: Particles::new(display, seed)?,
+            juice: Juice::new(effects),
+            controls,
+            controls_bob: 0.0,
             elapsed_time: 0.0,
-            spawn_time: 0.0,
-            game_over_delay: 0.0,
-            rand: rand::thread_rng(),
-            topbar: Topbar::new(display),
+            topbar: Topbar::new(display)?,
             paused: false,
-        }
+            recorder: Recorder::new(seed),
+            playback: None,
+            ghost: None,
+            world,
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.world.seed()
     }
 
-    pub fn restart(&mut self, display: &Display) {
-        self.game_over_delay = 0.0;
+    pub fn replay(&self) -> &Replay {
+        self.recorder.replay()
+    }
 
-        self.platforms[3].set_position(510.0, -100.0);
-        self.platforms[2].set_position(800.0, -150.0);
-        self.platforms[1].set_position(1060.0, 50.0);
-        self.platforms[0].set_position(LEFT + 100.0, -50.0);
+    pub fn play_back(&mut self, display: &Display, replay: Replay) -> Result<(), AssetError> {
+        self.restart(display, replay.seed)?;
+        self.playback = Some(Playback::new(replay));
+        Ok(())
+    }
 
-        for i in 0..self.platforms.len() {
-            self.platforms[i].set_type(Type::Plain);
-        }
+    pub fn race(&mut self, display: &Display, best: Option<&Replay>) -> Result<(), AssetError> {
+        self.ghost = best
+            .filter(|replay| replay.seed == self.world.seed())
+            .map(|replay| Ghost::new(display, replay.clone()))
+            .transpose()?;
+        Ok(())
+    }
 
-        self.controls[0].set_position(-210.0, 160.0);
-        self.controls[1].set_position(510.0, 160.0);
+    pub fn restart(&mut self, display: &Display, seed: u64) -> Result<(), AssetError> {
+        self.world.restart(seed);
+        self.recorder = Recorder::new(seed);
+        self.playback = None;
+        self.ghost = None;
+        self.previous.clone_from(&self.world);
+        self.controls_bob = 0.0;
 
         self.enemies.clear();
-        self.player.reset();
-        self.topbar.reset(display);
+        self.particles.clear(seed);
+        self.juice.reset();
+        self.topbar.reset();
+        self.sync(display, 0.0)
+    }
+
+    pub fn scores(&self) -> Scores {
+        self.world.scores
     }
 
     pub fn game_over(&mut self, dt: f32) -> bool {
-        if self.player.is_dead() {
-            self.game_over_delay += dt;
-            if self.game_over_delay > 3.0 {
-                return true;
-            }
-        }
-        false
+        self.world.game_over(dt)
     }
 
     pub fn paused(&self) -> bool {
@@ -104,174 +127,145 @@ impl Level {
         self.paused = false;
     }
 
-    fn get_new_platform_pos(&mut self, index: usize) -> (f32, f32) {
-        let mut x: f32;
-        let mut y: f32;
-        let w = self.platforms[index].width;
-        let h = self.platforms[index].height;
-        loop {
-            let mut intersects = false;
-            x = self.rand.gen_range(RIGHT + 100.0..SCREEN_WIDTH + RIGHT);
-            y = self.rand.gen_range(BOTTOM + 100.0..TOP - 200.0);
-            for j in 0..self.platforms.len() {
-                if overlap_x(
-                    Rect {
-                        x: x,
-                        y: y,
-                        w: w,
-                        h: h,
-                    },
-                    Rect {
-                        x: self.platforms[j].x,
-                        y: self.platforms[j].y,
-                        w: self.platforms[j].width,
-                        h: self.platforms[j].height,
-                    },
-                ) && index != j
-                {
-                    intersects = true;
-                    break;
-                }
-            }
-            if !intersects {
-                break;
-            }
-        }
-        (x, y)
-    }
-
-    fn regenerate_platform(&mut self, index: usize) {
-        let p = self.rand.gen_range(0..10);
-        if p < 5 {
-            self.platforms[index].set_type(Type::Fish);
-        } else if p >= 5 && p < 8 {
-            self.platforms[index].set_type(Type::Enemy);
-        } else {
-            self.platforms[index].set_type(Type::Plain);
-        }
-        let (x, y) = self.get_new_platform_pos(index);
-        self.platforms[index].set_position(x, y);
-    }
-
-    pub fn update(&mut self, input: &mut InputManager, display: &Display, dt: f32) {
+    pub fn update(
+        &mut self,
+        input: &mut InputManager,
+        display: &Display,
+        dt: f32,
+    ) -> Result<(), AssetError> {
         if self.paused {
-            return;
+            return Ok(());
         }
 
         if input.key_went_up(VirtualKeyCode::Escape) {
             self.paused = true;
         }
 
-        self.player.update(input, dt);
+        // hit-stop holds the world still, recording nothing, while the
+        // effects carry on
+        self.juice.update(dt);
+        if self.juice.frozen() {
+            self.previous.clone_from(&self.world);
+            self.particles.update(dt);
+            return Ok(());
+        }
 
-        for i in 0..self.platforms.len() {
-            self.platforms[i].update(display, dt);
+        let tick = self.world.tick;
+        let snapshot = match self.playback.as_mut() {
+            Some(playback) => playback.input_at(tick),
+            None => input.snapshot(),
+        };
+        self.recorder.record(tick, &snapshot);
+
+        self.previous.clone_from(&self.world);
+        self.world.update(&snapshot, dt);
+        self.particles.react(&self.world.events);
+        self.particles.update(dt);
+        self.juice.react(&self.world.events);
+
+        if let Some(ghost) = self.ghost.as_mut() {
+            ghost.update(dt);
+        }
 
-            match self.platforms[i].platform_type {
-                Type::Fish => {
-                    self.platforms[i].player_took_fish(display, &self.player, &mut self.topbar);
-                }
-                Type::Enemy => {
-                    self.platforms[i].player_vs_enemy(display, &mut self.player, &mut self.topbar);
-                }
-                _ => {}
+        if CONTROLS_POSITIONS[1].0 - self.world.camera.x > -SCREEN_WIDTH {
+            if self.elapsed_time > 999999. {
+                self.elapsed_time = 1.0;
             }
+            self.elapsed_time += dt;
 
-            if self.platforms[i].x + self.platforms[i].width / 2.0 < (-SCREEN_WIDTH) {
-                self.regenerate_platform(i);
-            }
+            let t = self.elapsed_time * 1.5;
+            self.controls_bob += t.sin() * 0.04;
+        }
 
-            if player_landed(&self.player, &self.platforms[i]) {
-                self.player.velocity[1] = 0.0;
-            }
+        self.sync(display, dt)
+    }
 
-            if self.platforms[i].player_is_on(&mut self.player) {
-                break;
-            }
-        }
+    // Enemies spawned since the last step get sprites of their own, which is
+    // the only loading done mid-run.
+    fn sync(&mut self, display: &Display, dt: f32) -> Result<(), AssetError> {
+        self.player.update(&self.world.player, dt);
 
-        for i in 0..self.enemies.len() {
-            self.enemies[i].update(dt);
+        for (platform, state) in self.platforms.iter_mut().zip(self.world.platforms.iter()) {
+            platform.update(state, dt);
+        }
 
-            if !self.enemies[i].is_dead() {
-                self.enemies[i].translate(-120.0 * dt, 0.0);
-            } else {
-                self.enemies[i].apply_gravity(dt);
+        for (i, state) in self.world.enemies.iter().enumerate() {
+            if i == self.enemies.len() {
+                self.enemies.push(Enemy::new(display, state.species)?);
             }
+            self.enemies[i].update(state, dt);
+        }
 
-            if self.enemies[i].x <= LEFT - self.enemies[i].width
-                || self.enemies[i].y <= BOTTOM - self.enemies[i].height
-            {
-                let x = self.rand.gen_range(RIGHT..SCREEN_WIDTH);
-                let y = self.rand.gen_range(BOTTOM + 40.0..TOP - 40.0);
-                self.enemies[i].set_position(x, y);
-                self.enemies[i].set_dead(false);
-            }
+        self.topbar.update(&self.world.scores);
+        Ok(())
+    }
 
-            self.player.check_interaction(&mut self.enemies[i], &mut self.topbar, display);
-        }
+    pub fn camera_x(&self, alpha: f32) -> f32 {
+        let (x, _) = interpolate(
+            (self.previous.camera.x, 0.0),
+            (self.world.camera.x, 0.0),
+            alpha,
+        );
+        x
+    }
 
-        if self.controls[1].x > -SCREEN_WIDTH {
-            if self.elapsed_time > 999999. {
-                self.elapsed_time = 1.0;
-            }
-            self.elapsed_time += dt;
+    fn set_positions(&mut self, alpha: f32) {
+        let (world, previous) = (&self.world, &self.previous);
 
-            for i in 0..self.controls.len() {
-                let t = self.elapsed_time * 1.5;
-                let y = t.sin() * 0.04;
+        let (x, y) = interpolate(
+            (previous.player.body.x, previous.player.body.y),
+            (world.player.body.x, world.player.body.y),
+            alpha,
+        );
+        self.player.set_position(x, y);
 
-                self.controls[i].translate(0.0, y);
-            }
+        for (i, platform) in self.platforms.iter_mut().enumerate() {
+            platform.set_position(&previous.platforms[i], &world.platforms[i], alpha);
         }
 
-        if self.player.is_moving_right {
-            for i in 0..self.platforms.len() {
-                self.platforms[i].translate(-80.0 * dt, 0.0);
-            }
-            for i in 0..self.controls.len() {
-                self.controls[i].translate(-80.0 * dt, 0.0);
-            }
+        for (i, enemy) in self.enemies.iter_mut().enumerate() {
+            let state = &world.enemies[i];
+            let prev = previous.enemies.get(i).unwrap_or(state);
+            let (x, y) = interpolate(
+                (prev.body.x, prev.body.y),
+                (state.body.x, state.body.y),
+                alpha,
+            );
+            enemy.set_position(x, y);
         }
 
-        if self.player.distance > 0.5 {
-            self.topbar.increment_distance(display);
-            self.player.distance = 0.0;
+        for (control, (x, y)) in self.controls.iter_mut().zip(CONTROLS_POSITIONS) {
+            control.set_position(x, y + self.controls_bob);
         }
+    }
 
-        self.spawn_time += dt;
+    pub fn draw(&mut self, batch: &mut SpriteBatch, alpha: f32) {
+        self.set_positions(alpha);
+        let (shake_x, shake_y) = self.juice.shake();
+        batch.set_offset(-self.camera_x(alpha) + shake_x, shake_y);
 
-        if self.spawn_time >= SPAWN_DELAY {
-            self.enemies.push(Enemy::new(display, Species::Flying));
-            let x = self.rand.gen_range(RIGHT..SCREEN_WIDTH);
-            let y = self.rand.gen_range(BOTTOM..TOP);
-            let i = self.enemies.len() - 1;
-            self.enemies[i].set_position(x, y);
-            self.spawn_time = 0.0;
+        for control in self.controls.iter() {
+            control.draw(batch);
         }
-    }
-
-    pub fn draw(&mut self, target: &mut Frame, program: &Program) {
-        for i in 0..self.controls.len() {
-            self.controls[i].draw(target, program);
+        for platform in self.platforms.iter_mut() {
+            platform.draw(batch);
         }
-        for i in (0..=self.platforms.len() - 1).rev() {
-            self.platforms[i].draw(target, program);
+
+        if let Some(ghost) = self.ghost.as_mut() {
+            ghost.draw(batch, alpha);
         }
 
-        self.player.draw(target, program);
+        self.player.draw(batch);
 
-        for i in 0..self.enemies.len() {
-            self.enemies[i].draw(target, program);
+        for enemy in self.enemies.iter_mut() {
+            enemy.draw(batch);
         }
 
-        self.topbar.draw(target, program);
-    }
-}
+        self.particles.draw(batch);
 
-fn player_landed(player: &Player, platform: &Platform) -> bool {
-    player.x + player.width / 2. >= platform.x - platform.width / 2.
-        && player.x - player.width / 2. <= platform.x + platform.width / 2.
-        && player.y - player.height / 2. + player.velocity[1] <= platform.y + platform.height / 2.
-        && player.y - player.height / 2. >= platform.y + platform.height / 2.0
+        batch.set_offset(0.0, 0.0);
+        batch.set_layer(HUD_LAYER);
+        self.topbar.draw(batch);
+        self.juice.draw(batch);
+    }
 }
@@ -0,0 +1,61 @@
+use glium::Display;
+
+use crate::{
+    assets::AssetError,
+    batch::{SpriteBatch, GHOST_DEPTH},
+    player::Player,
+    replay::{Playback, Replay},
+    sim::{interpolate, World},
+};
+
+const GHOST_ALPHA: f32 = 0.4;
+// Turns the ghost's colours around so it can't be mistaken for the player.
+const GHOST_HUE_SHIFT: f32 = 180.0;
+
+// A replay of the best run, simulated alongside the live one on the same
+// seed. The platform layout comes from the seed alone and both runs share
+// world coordinates, so the ghost is drawn with the live run's camera.
+pub struct Ghost {
+    world: World,
+    playback: Playback,
+    sprite: Player,
+    previous: (f32, f32),
+}
+
+impl Ghost {
+    pub fn new(display: &Display, replay: Replay) -> Result<Self, AssetError> {
+        let world = World::new(replay.seed);
+        let mut sprite = Player::new(display)?;
+        sprite.set_alpha(GHOST_ALPHA);
+        sprite.set_hue_shift(GHOST_HUE_SHIFT);
+        sprite.set_depth(GHOST_DEPTH);
+
+        let mut ghost = Self {
+            previous: (0.0, 0.0),
+            world,
+            playback: Playback::new(replay),
+            sprite,
+        };
+        ghost.previous = ghost.position();
+        Ok(ghost)
+    }
+
+    fn position(&self) -> (f32, f32) {
+        let body = &self.world.player.body;
+        (body.x, body.y)
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.previous = self.position();
+
+        let input = self.playback.input_at(self.world.tick);
+        self.world.update(&input, dt);
+        self.sprite.update(&self.world.player, dt);
+    }
+
+    pub fn draw(&mut self, batch: &mut SpriteBatch, alpha: f32) {
+        let (x, y) = interpolate(self.previous, self.position(), alpha);
+        self.sprite.set_position(x, y);
+        self.sprite.draw(batch);
+    }
+}
@@ -1,144 +1,281 @@
-use glium::{Display, Frame, Program};
-use crate::{texture::{Texture, Transform}, shape::{LEFT, TOP, RIGHT}};
+use crate::{
+    assets::{self, AssetError, Sprite},
+    batch::{SpriteBatch, LABEL_DEPTH},
+    shape::{LEFT, RIGHT, TOP},
+    sim::Scores,
+    text::Align,
+    texture::{Texture, Transform},
+};
+use glium::Display;
 
-pub struct Score {
-    value: u32,
+// How long an animated change takes to count up to its new value, in
+// seconds.
+const COUNT_TIME: f32 = 1.0;
+
+// Zero padded to `min_digits`, with the padding grouped along with the rest
+// when there are separators.
+fn format_number(value: u64, min_digits: usize, separators: bool) -> String {
+    let digits = format!("{:0width$}", value, width = min_digits);
+    if !separators {
+        return digits;
+    }
+    let mut text = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            text.push(',');
+        }
+        text.push(digit);
+    }
+    text
+}
+
+// A number drawn with the digit images. It's positioned like text: `x` is
+// its left edge, centre or right edge depending on the alignment, and `y`
+// its centre.
+pub struct NumberDisplay {
+    value: u64,
+    shown: u64,
+    // the value a count started from and how far into it we are
+    counting: Option<(u64, f32)>,
+    digits: Vec<Sprite>,
+    comma: Sprite,
     textures: Vec<Texture>,
+    align: Align,
+    separators: bool,
+    min_digits: usize,
+    x: f32,
+    y: f32,
+    depth: i32,
 }
 
-impl Score {
-    pub fn new(display: &Display) -> Self {
-        Self {
+impl NumberDisplay {
+    pub fn new(display: &Display) -> Result<Self, AssetError> {
+        let mut number = Self {
             value: 0,
-            textures: vec![Texture::new("./res/digits/0.png", display)],
-        }
+            shown: 0,
+            counting: None,
+            digits: (0..10)
+                .map(|digit| assets::sprite(&format!("./res/digits/{}.png", digit), display))
+                .collect::<Result<_, _>>()?,
+            comma: assets::sprite("./res/digits/comma.png", display)?,
+            textures: vec![],
+            align: Align::Left,
+            separators: false,
+            min_digits: 1,
+            x: 0.0,
+            y: 0.0,
+            depth: 0,
+        };
+        number.layout();
+        Ok(number)
+    }
+
+    // Jumps straight to `value`.
+    pub fn set(&mut self, value: u64) {
+        self.value = value;
+        self.counting = None;
+        self.show(value);
+    }
+
+    // Rolls the number from what's shown now to `value` over COUNT_TIME.
+    pub fn count_to(&mut self, value: u64) {
+        self.value = value;
+        self.counting = Some((self.shown, 0.0));
+    }
+
+    pub fn set_align(&mut self, align: Align) {
+        self.align = align;
+        self.layout();
+    }
+
+    // Groups the digits in threes with commas.
+    pub fn set_separators(&mut self, separators: bool) {
+        self.separators = separators;
+        self.layout();
+    }
+
+    // Pads with zeros up to `digits` digits.
+    pub fn set_leading_zeros(&mut self, digits: usize) {
+        self.min_digits = digits.max(1);
+        self.layout();
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+        self.layout();
     }
 
-    pub fn reset(&mut self, display: &Display) {
-        self.value = 0;
-
-        let i = self.textures.len();
-        let x = self.textures[i - 1].x;
-        let y = self.textures[i - 1].y;
-        self.textures.clear();
-        self.textures.push(Texture::new("./res/digits/0.png", display));
-        self.textures[0].set_position(x, y);
-    }
-
-    pub fn increment(&mut self, display: &Display) {
-        self.value += 1;
-        let mut temp = self.value;
-        let mut i = 0;
-        while temp > 0 && i < self.textures.len() {
-            let (x, y) = (self.textures[i].x, self.textures[i].y);
-            if temp % 10 != 0 {
-                self.textures[i] =
-                    Texture::new(format!("./res/digits/{}.png", temp % 10).as_str(), display);
-                self.textures[i].set_position(x, y);
-                return;
-            } else {
-                self.textures[i] = Texture::new("./res/digits/0.png", display);
-                self.textures[i].set_position(x, y);
-            }
-            i += 1;
-            temp /= 10;
+    pub fn set_depth(&mut self, depth: i32) {
+        self.depth = depth;
+        self.layout();
+    }
+
+    pub fn width(&self) -> f32 {
+        self.textures.iter().map(|t| t.width).sum()
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let Some((from, elapsed)) = self.counting else {
+            return;
+        };
+        let elapsed = elapsed + dt;
+        if elapsed >= COUNT_TIME {
+            self.set(self.value);
+            return;
         }
+        self.counting = Some((from, elapsed));
+
+        // eases out, so the last few steps tick over slowly
+        let t = elapsed / COUNT_TIME;
+        let t = 1.0 - (1.0 - t) * (1.0 - t);
+        let shown = from as f64 + (self.value as f64 - from as f64) * t as f64;
+        self.show(shown.round() as u64);
+    }
 
-        let i = self.textures.len();
-        let x = self.textures[i - 1].x;
-        let y = self.textures[i - 1].y;
-        self.textures
-            .push(Texture::new("./res/digits/1.png", display));
-        let i = self.textures.len();
-        self.textures[i - 1].set_position(x, y);
-
-        for i in 0..self.textures.len() - 1 {
-            let x = self.textures[i].x;
-            let w = self.textures[i].width;
-            self.textures[i].set_x(x + w);
+    fn show(&mut self, value: u64) {
+        if value == self.shown && !self.textures.is_empty() {
+            return;
         }
+        self.shown = value;
+        self.layout();
     }
 
-    pub fn set_position(&mut self, x: f32, y: f32) {
-        self.textures[0].set_position(x, y);
+    fn layout(&mut self) {
+        self.textures = format_number(self.shown, self.min_digits, self.separators)
+            .chars()
+            .map(|c| match c.to_digit(10) {
+                Some(digit) => Texture::from_sprite(self.digits[digit as usize].clone()),
+                None => Texture::from_sprite(self.comma.clone()),
+            })
+            .collect();
+
+        let width = self.width();
+        let mut x = match self.align {
+            Align::Left => self.x,
+            Align::Center => self.x - width / 2.0,
+            Align::Right => self.x - width,
+        };
+        for texture in self.textures.iter_mut() {
+            texture.set_depth(self.depth);
+            texture.set_position(x + texture.width / 2.0, self.y);
+            x += texture.width;
+        }
     }
 
-    pub fn draw(&self, target: &mut glium::Frame, program: &glium::Program) {
-        for i in 0..self.textures.len() {
-            self.textures[i].draw(target, program);
+    pub fn draw(&self, batch: &mut SpriteBatch) {
+        for texture in self.textures.iter() {
+            texture.draw(batch);
         }
     }
 }
 
 pub struct Topbar {
-    fish_score: Score,
+    fish_score: NumberDisplay,
     fish_label: Texture,
-    enemy_score: Score,
+    enemy_score: NumberDisplay,
     enemy_label: Texture,
     flag_label: Texture,
-    distance: Score,
+    distance: NumberDisplay,
     stop_button: Texture,
 }
 
 impl Topbar {
-    pub fn new(display: &Display) -> Self {
-        let mut fish_label = Texture::new("./res/gui/fish_label.png", display);
+    pub fn new(display: &Display) -> Result<Self, AssetError> {
+        let mut fish_label = Texture::new("./res/gui/fish_label.png", display)?;
         fish_label.set_position(LEFT + 32.0, TOP - 32.0);
 
-        let mut fish_score = Score::new(display);
-        fish_score.set_position(LEFT + 80.0, TOP - 32.0);
+        let mut fish_score = NumberDisplay::new(display)?;
+        fish_score.set_depth(LABEL_DEPTH);
+        fish_score.set_position(LEFT + 64.0, TOP - 32.0);
 
-        let mut enemy_score = Score::new(display);
-        enemy_score.set_position(LEFT + 268.0, TOP - 32.0);
+        let mut enemy_score = NumberDisplay::new(display)?;
+        enemy_score.set_depth(LABEL_DEPTH);
+        enemy_score.set_position(LEFT + 252.0, TOP - 32.0);
 
-        let mut enemy_label = Texture::new("./res/gui/monsta.png", display);
+        let mut enemy_label = Texture::new("./res/gui/monsta.png", display)?;
         enemy_label.set_position(LEFT + 220.0, TOP - 30.0);
 
-        let mut flag_label = Texture::new("./res/gui/flag.png", display);
+        let mut flag_label = Texture::new("./res/gui/flag.png", display)?;
         flag_label.set_position(32.0, TOP - 32.0);
 
-        let mut distance = Score::new(display);
-        distance.set_position(80.0, TOP - 32.0);
+        let mut distance = NumberDisplay::new(display)?;
+        distance.set_depth(LABEL_DEPTH);
+        distance.set_separators(true);
+        distance.set_position(64.0, TOP - 32.0);
 
-        let mut stop_button = Texture::new("./res/gui/pause_button.png", display);
+        let mut stop_button = Texture::new("./res/gui/pause_button.png", display)?;
         stop_button.set_position(RIGHT - 40.0, TOP - 32.0);
 
-        Self {
-            fish_score: fish_score,
-            fish_label: fish_label,
-            enemy_score: enemy_score,
-            enemy_label: enemy_label,
-            flag_label: flag_label,
-            distance: distance,
-            stop_button: stop_button,
-        }
+        Ok(Self {
+            fish_score,
+            fish_label,
+            enemy_score,
+            enemy_label,
+            flag_label,
+            distance,
+            stop_button,
+        })
+    }
+
+    pub fn update(&mut self, scores: &Scores) {
+        self.fish_score.set(scores.fish as u64);
+        self.enemy_score.set(scores.enemies as u64);
+        self.distance.set(scores.distance as u64);
     }
 
-    pub fn increment_fish_count(&mut self, display: &Display) {
-        self.fish_score.increment(display);
+    pub fn reset(&mut self) {
+        self.fish_score.set(0);
+        self.enemy_score.set(0);
+        self.distance.set(0);
     }
 
-    pub fn increment_enemy_count(&mut self, display: &Display) {
-        self.enemy_score.increment(display);
+    pub fn draw(&mut self, batch: &mut SpriteBatch) {
+        self.fish_score.draw(batch);
+        self.fish_label.draw(batch);
+        self.enemy_label.draw(batch);
+        self.enemy_score.draw(batch);
+        self.flag_label.draw(batch);
+        self.distance.draw(batch);
+        self.stop_button.draw(batch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_numbers() {
+        assert_eq!(format_number(0, 1, false), "0");
+        assert_eq!(format_number(1234567, 1, false), "1234567");
+        assert_eq!(format_number(u64::MAX, 1, false), "18446744073709551615");
     }
 
-    pub fn increment_distance(&mut self, display: &Display) {
-        self.distance.increment(display);
+    #[test]
+    fn separators_group_in_threes() {
+        assert_eq!(format_number(0, 1, true), "0");
+        assert_eq!(format_number(999, 1, true), "999");
+        assert_eq!(format_number(1000, 1, true), "1,000");
+        assert_eq!(format_number(1234567, 1, true), "1,234,567");
+        assert_eq!(
+            format_number(u64::MAX, 1, true),
+            "18,446,744,073,709,551,615"
+        );
     }
 
-    pub fn reset(&mut self, display: &Display) {
-        self.fish_score.reset(display);
-        self.enemy_score.reset(display);
-        self.distance.reset(display);
+    #[test]
+    fn leading_zeros() {
+        assert_eq!(format_number(0, 6, false), "000000");
+        assert_eq!(format_number(42, 6, false), "000042");
+        // never cut short
+        assert_eq!(format_number(1234567, 6, false), "1234567");
     }
 
-    pub fn draw(&mut self, target: &mut Frame, program: &Program) {
-        self.fish_score.draw(target, program);
-        self.fish_label.draw(target, program);
-        self.enemy_label.draw(target, program);
-        self.enemy_score.draw(target, program);
-        self.flag_label.draw(target, program);
-        self.distance.draw(target, program);
-        self.stop_button.draw(target, program);
+    #[test]
+    fn leading_zeros_with_separators() {
+        assert_eq!(format_number(42, 6, true), "000,042");
+        assert_eq!(format_number(42, 4, true), "0,042");
+        assert_eq!(format_number(1234567, 6, true), "1,234,567");
     }
 }
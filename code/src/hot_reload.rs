@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::fs::{metadata, read_to_string};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use glium::{Display, Program};
+
+use crate::assets::{self, RES_DIR};
+
+pub const SHADER_DIR: &str = "shaders";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Development mode: watches res/ and shaders/ under the asset root by
+// modification time and pushes changes into the running game.
+pub struct HotReload {
+    root: PathBuf,
+    mtimes: HashMap<PathBuf, SystemTime>,
+    last_poll: Instant,
+}
+
+impl HotReload {
+    pub fn new(root: &Path) -> Self {
+        let mut hot_reload = Self {
+            root: root.to_path_buf(),
+            mtimes: HashMap::new(),
+            last_poll: Instant::now(),
+        };
+        hot_reload.mtimes = hot_reload.scan();
+        hot_reload
+    }
+
+    fn scan(&self) -> HashMap<PathBuf, SystemTime> {
+        let mut keys = vec![];
+        assets::walk(&self.root, Path::new(RES_DIR), &mut keys);
+        assets::walk(&self.root, Path::new(SHADER_DIR), &mut keys);
+
+        keys.into_iter()
+            .filter_map(|key| {
+                let modified = metadata(self.root.join(&key)).ok()?.modified().ok()?;
+                Some((key, modified))
+            })
+            .collect()
+    }
+
+    pub fn poll(&mut self, display: &Display, program: &mut Program) {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return;
+        }
+        self.last_poll = Instant::now();
+
+        let mtimes = self.scan();
+        let mut shaders_changed = false;
+        for (key, modified) in mtimes.iter() {
+            if self.mtimes.get(key) == Some(modified) {
+                continue;
+            }
+
+            if key.starts_with(SHADER_DIR) {
+                shaders_changed = true;
+            } else if key.extension().is_some_and(|ext| ext == "png") {
+                match assets::reload(key) {
+                    Ok(()) => println!("reloaded {}", key.display()),
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+        }
+        self.mtimes = mtimes;
+
+        // a shader that doesn't compile leaves the old program running
+        if shaders_changed {
+            match compile(display, &self.root) {
+                Ok(new_program) => {
+                    *program = new_program;
+                    println!("recompiled shaders");
+                }
+                Err(e) => eprintln!("shaders failed to compile, keeping the old ones:\n{}", e),
+            }
+        }
+    }
+}
+
+fn compile(display: &Display, root: &Path) -> Result<Program, String> {
+    let read = |name: &str| {
+        let path = root.join(SHADER_DIR).join(name);
+        read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))
+    };
+    let vertex = read("vertex.glsl")?;
+    let fragment = read("fragment.glsl")?;
+
+    Program::from_source(display, &vertex, &fragment, None).map_err(|e| e.to_string())
+}
@@ -1,5 +1,7 @@
 use glium::glutin::event::{ElementState, VirtualKeyCode};
 
+use crate::sim::Input;
+
 pub struct InputManager {
     keys: [bool; 163],
     prev_keys: [bool; 163],
@@ -31,6 +33,14 @@ impl InputManager {
         !self.keys[key as usize] && self.key_changed(key)
     }
 
+    pub fn snapshot(&self) -> Input {
+        Input {
+            flap: self.key_down(VirtualKeyCode::Up),
+            left: self.key_down(VirtualKeyCode::Left),
+            right: self.key_down(VirtualKeyCode::Right),
+        }
+    }
+
     pub fn update(&mut self, state: ElementState, key: VirtualKeyCode) {
         if state != ElementState::Released {
             if !self.keys[key as usize] {
@@ -0,0 +1,123 @@
+use crate::batch::{SpriteBatch, EFFECT_DEPTH};
+use crate::shape::{Rectangle, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::sim::Event;
+
+// Furthest the view is thrown at full trauma, in pixels.
+const MAX_SHAKE: f32 = 14.0;
+// Trauma lost per second.
+const TRAUMA_DECAY: f32 = 1.6;
+
+// How hard each event hits: trauma added, steps the simulation holds
+// still for, and the colour and length of the flash.
+struct Impact {
+    trauma: f32,
+    hit_stop: u32,
+    flash: [f32; 4],
+    flash_time: f32,
+}
+
+const STOMP: Impact = Impact {
+    trauma: 0.35,
+    hit_stop: 6,
+    flash: [1.0, 1.0, 1.0, 0.3],
+    flash_time: 0.12,
+};
+
+const DEATH: Impact = Impact {
+    trauma: 0.8,
+    hit_stop: 18,
+    flash: [1.0, 0.1, 0.1, 0.5],
+    flash_time: 0.4,
+};
+
+pub struct Juice {
+    enabled: bool,
+    trauma: f32,
+    time: f32,
+    hit_stop: u32,
+    flash: Rectangle,
+    flash_color: [f32; 4],
+    flash_time: f32,
+    flash_left: f32,
+}
+
+impl Juice {
+    pub fn new(enabled: bool) -> Self {
+        let mut flash = Rectangle::new(SCREEN_WIDTH, SCREEN_HEIGHT);
+        flash.set_depth(EFFECT_DEPTH);
+
+        Self {
+            enabled,
+            trauma: 0.0,
+            time: 0.0,
+            hit_stop: 0,
+            flash,
+            flash_color: [0.0; 4],
+            flash_time: 0.0,
+            flash_left: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.trauma = 0.0;
+        self.hit_stop = 0;
+        self.flash_left = 0.0;
+    }
+
+    fn hit(&mut self, impact: &Impact) {
+        self.trauma = (self.trauma + impact.trauma).min(1.0);
+        self.hit_stop = self.hit_stop.max(impact.hit_stop);
+        self.flash_color = impact.flash;
+        self.flash_time = impact.flash_time;
+        self.flash_left = impact.flash_time;
+    }
+
+    pub fn react(&mut self, events: &[Event]) {
+        if !self.enabled {
+            return;
+        }
+        for event in events {
+            match event {
+                Event::Stomp { .. } => self.hit(&STOMP),
+                Event::Death { .. } => self.hit(&DEATH),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.trauma = (self.trauma - TRAUMA_DECAY * dt).max(0.0);
+        self.flash_left = (self.flash_left - dt).max(0.0);
+    }
+
+    // Uses up one step of hit-stop, if any is left.
+    pub fn frozen(&mut self) -> bool {
+        if self.hit_stop == 0 {
+            return false;
+        }
+        self.hit_stop -= 1;
+        true
+    }
+
+    // Offset for the world this frame. Squaring the trauma keeps small
+    // knocks subtle, and the sines give a smooth wobble rather than jitter.
+    pub fn shake(&self) -> (f32, f32) {
+        let shake = self.trauma * self.trauma * MAX_SHAKE;
+        let t = self.time * 40.0;
+        (
+            shake * ((t * 1.3).sin() + (t * 2.9).sin()) / 2.0,
+            shake * ((t * 1.7 + 1.0).sin() + (t * 3.1 + 2.0).sin()) / 2.0,
+        )
+    }
+
+    pub fn draw(&mut self, batch: &mut SpriteBatch) {
+        if self.flash_left <= 0.0 {
+            return;
+        }
+        let mut color = self.flash_color;
+        color[3] *= self.flash_left / self.flash_time;
+        self.flash.set_color(color);
+        self.flash.draw(batch);
+    }
+}
@@ -1,56 +1,127 @@
 extern crate glium;
 extern crate image;
 
+mod assets;
 mod background;
+mod batch;
+mod capture;
 mod collision;
 mod enemy;
 mod game;
+mod ghost;
 mod gui;
+mod hot_reload;
 mod input_mgr;
+mod juice;
+mod options;
+mod particles;
 mod platform;
 mod player;
+mod renderer;
+mod replay;
+mod rng;
 mod screen_mgr;
 mod screens;
 mod shape;
+mod sim;
+mod sky;
+mod text;
 mod texture;
 
+use crate::batch::SpriteBatch;
+use crate::capture::Capture;
+use crate::hot_reload::HotReload;
+use crate::options::Options;
+use crate::renderer::Renderer;
+use crate::replay::Replay;
 use crate::screen_mgr::ScreenMgr;
-use std::time::Instant;
+use crate::sim::FIXED_DT;
+use std::time::{Duration, Instant};
+
+const MAX_FRAME_TIME: f32 = 0.25;
 
 fn main() {
+    let options = match Options::from_args() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(message) = assets::init(options.assets.as_deref()) {
+        eprintln!("{}", message);
+        std::process::exit(1);
+    }
+
+    // debug builds carry on with placeholders so art can be worked on
+    // while some of it is broken
+    let errors = assets::validate();
+    if !errors.is_empty() {
+        eprintln!("{} asset(s) failed to load:", errors.len());
+        for error in &errors {
+            eprintln!("  {}", error);
+        }
+        if !cfg!(debug_assertions) {
+            std::process::exit(1);
+        }
+    }
+
+    use glium::glutin;
     use glium::glutin::dpi::PhysicalSize;
-    use glium::{glutin, Surface};
+
+    let replay = match &options.replay {
+        Some(path) => match Replay::load(path) {
+            Ok(replay) => Some(replay),
+            Err(e) => {
+                eprintln!("Unable to load replay {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
 
     let event_loop = glutin::event_loop::EventLoop::new();
     let wb = glutin::window::WindowBuilder::new()
         .with_inner_size(PhysicalSize::new(768, 576))
-        .with_title(format!("Ubiland Saga"))
-        .with_resizable(false);
-    let cb = glutin::ContextBuilder::new();
+        .with_title("Ubiland Saga".to_string())
+        .with_resizable(true);
+    let cb = glutin::ContextBuilder::new().with_vsync(true);
     let display = glium::Display::new(wb, cb, &event_loop).unwrap();
 
     let vertex_shader_src = include_str!("../shaders/vertex.glsl");
 
     let fragment_shader_src = include_str!("../shaders/fragment.glsl");
 
-    let program =
+    let mut program =
         glium::Program::from_source(&display, vertex_shader_src, fragment_shader_src, None)
             .unwrap();
 
-    let mut screen_mgr = ScreenMgr::new(&display);
+    // the shaders baked into the binary are only swapped out once one of
+    // the files on disk changes
+    let mut hot_reload = None;
+    if options.dev {
+        match assets::root() {
+            Some(root) => hot_reload = Some(HotReload::new(root)),
+            None => eprintln!("--dev needs assets on disk, the embedded ones can't be watched"),
+        }
+    }
+
+    let mut renderer = Renderer::new(&display, options.scaling, options.fullscreen);
+    let mut capture = Capture::new(options.capture_dir.clone(), options.gif_seconds);
+    let mut screen_mgr = match ScreenMgr::new(&display, options, replay) {
+        Ok(screen_mgr) => screen_mgr,
+        Err(e) => {
+            eprintln!("Unable to load the game: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut batch = SpriteBatch::new(&display);
 
     let mut previous_frame_time = Instant::now();
+    let mut accumulator = 0.0;
 
     event_loop.run(move |ev, _, control_flow| {
-        let current_time = Instant::now();
-        let elapsed_time = current_time.duration_since(previous_frame_time);
-        let delta_time = elapsed_time.as_secs_f32();
-        previous_frame_time = current_time;
-
-        let next_frame_time =
-            std::time::Instant::now() + std::time::Duration::from_nanos(delta_time as u64);
-        *control_flow = glutin::event_loop::ControlFlow::WaitUntil(next_frame_time);
-
         match ev {
             glutin::event::Event::WindowEvent { event, .. } => match event {
                 glutin::event::WindowEvent::KeyboardInput {
@@ -58,6 +129,18 @@ fn main() {
                     input,
                     is_synthetic: _,
                 } => {
+                    if input.state == glutin::event::ElementState::Pressed {
+                        match input.virtual_keycode {
+                            Some(glutin::event::VirtualKeyCode::F11) => {
+                                renderer.toggle_fullscreen(&display)
+                            }
+                            Some(glutin::event::VirtualKeyCode::F12) => {
+                                capture.screenshot(&renderer)
+                            }
+                            Some(glutin::event::VirtualKeyCode::F10) => capture.save_clip(),
+                            _ => (),
+                        }
+                    }
                     screen_mgr.input.update(
                         input.state,
                         input
@@ -67,24 +150,50 @@ fn main() {
                 }
                 glutin::event::WindowEvent::CloseRequested => {
                     *control_flow = glutin::event_loop::ControlFlow::Exit;
-                    return;
                 }
-                _ => return,
+                _ => (),
             },
-            _ => (),
-        }
-        //update game
-        screen_mgr.update(&display, delta_time);
+            glutin::event::Event::MainEventsCleared => {
+                let current_time = Instant::now();
+                let elapsed_time = current_time.duration_since(previous_frame_time);
+                previous_frame_time = current_time;
 
-        if screen_mgr.exited() {
-            *control_flow = glutin::event_loop::ControlFlow::Exit;
-            return;
-        }
+                // a long stall (window drag, breakpoint) would otherwise queue
+                // up seconds of steps and freeze while catching up
+                accumulator += elapsed_time.as_secs_f32().min(MAX_FRAME_TIME);
+
+                //update game
+                while accumulator >= FIXED_DT {
+                    if let Err(e) = screen_mgr.update(&display, FIXED_DT) {
+                        eprintln!("Unable to load the game: {}", e);
+                        *control_flow = glutin::event_loop::ControlFlow::Exit;
+                        return;
+                    }
+                    accumulator -= FIXED_DT;
+
+                    if screen_mgr.exited() {
+                        *control_flow = glutin::event_loop::ControlFlow::Exit;
+                        return;
+                    }
+                }
+
+                if let Some(hot_reload) = hot_reload.as_mut() {
+                    hot_reload.poll(&display, &mut program);
+                }
 
-        let mut target = display.draw();
-        target.clear_color(1.0, 1.0, 1.0, 1.0);
-        //draw game
-        screen_mgr.draw(&mut target, &program);
-        target.finish().unwrap();
+                //draw game
+                let alpha = accumulator / FIXED_DT;
+                renderer.render(&display, &program, &mut batch, |batch| {
+                    screen_mgr.draw(batch, alpha)
+                });
+                capture.update(&renderer, elapsed_time.as_secs_f32());
+
+                // sleep until the next step is due rather than spinning, in
+                // case vsync is off or ignored by the driver
+                let next_step = current_time + Duration::from_secs_f32(FIXED_DT - accumulator);
+                *control_flow = glutin::event_loop::ControlFlow::WaitUntil(next_step);
+            }
+            _ => (),
+        }
     });
 }
@@ -0,0 +1,126 @@
+use std::{env, path::PathBuf};
+
+use crate::capture::{CAPTURE_DIR, MAX_GIF_SECONDS};
+use crate::renderer::Scaling;
+
+const USAGE: &str = "usage: code [--seed N] [--replay FILE] [--ghost] [--assets DIR] [--dev]\n            [--scale integer|aspect] [--fullscreen] [--no-weather]
+            [--reduce-effects] [--captures DIR] [--gif-seconds N]";
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub seed: Option<u64>,
+    pub replay: Option<PathBuf>,
+    pub ghost: bool,
+    pub assets: Option<PathBuf>,
+    pub dev: bool,
+    pub scaling: Scaling,
+    pub fullscreen: bool,
+    pub weather: bool,
+    pub effects: bool,
+    pub capture_dir: PathBuf,
+    pub gif_seconds: f32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            seed: None,
+            replay: None,
+            ghost: false,
+            assets: None,
+            dev: false,
+            scaling: Scaling::default(),
+            fullscreen: false,
+            weather: true,
+            effects: true,
+            capture_dir: PathBuf::from(CAPTURE_DIR),
+            gif_seconds: 0.0,
+        }
+    }
+}
+
+// Infinite and huge lengths would size the frame buffer past what memory
+// can hold, so clips are capped.
+fn gif_seconds(value: &str) -> Result<f32, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|seconds: &f32| (0.0..=MAX_GIF_SECONDS).contains(seconds))
+        .ok_or_else(|| {
+            format!(
+                "invalid gif length: {}, it must be 0 to {} seconds",
+                value, MAX_GIF_SECONDS
+            )
+        })
+}
+
+impl Options {
+    pub fn from_args() -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let value = args.next().ok_or("--seed needs a value")?;
+                    let seed = value
+                        .parse()
+                        .map_err(|_| format!("invalid seed: {}", value))?;
+                    options.seed = Some(seed);
+                }
+                "--replay" => {
+                    let value = args.next().ok_or("--replay needs a file")?;
+                    options.replay = Some(PathBuf::from(value));
+                }
+                "--ghost" => options.ghost = true,
+                "--assets" => {
+                    let value = args.next().ok_or("--assets needs a directory")?;
+                    options.assets = Some(PathBuf::from(value));
+                }
+                "--dev" => options.dev = true,
+                "--scale" => {
+                    let value = args.next().ok_or("--scale needs a mode")?;
+                    options.scaling = Scaling::from_name(&value)
+                        .ok_or_else(|| format!("invalid scale mode: {}", value))?;
+                }
+                "--fullscreen" => options.fullscreen = true,
+                "--no-weather" => options.weather = false,
+                "--reduce-effects" => options.effects = false,
+                "--captures" => {
+                    let value = args.next().ok_or("--captures needs a directory")?;
+                    options.capture_dir = PathBuf::from(value);
+                }
+                "--gif-seconds" => {
+                    let value = args.next().ok_or("--gif-seconds needs a value")?;
+                    options.gif_seconds = gif_seconds(&value)?;
+                }
+                "--help" | "-h" => return Err(USAGE.to_string()),
+                _ => return Err(format!("unknown argument: {}\n{}", arg, USAGE)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gif_seconds_accepts_zero_to_the_cap() {
+        assert_eq!(gif_seconds("0"), Ok(0.0));
+        assert_eq!(gif_seconds("2.5"), Ok(2.5));
+        assert_eq!(
+            gif_seconds(&MAX_GIF_SECONDS.to_string()),
+            Ok(MAX_GIF_SECONDS)
+        );
+    }
+
+    #[test]
+    fn gif_seconds_rejects_the_rest() {
+        for value in ["-1", "10.5", "1e12", "inf", "-inf", "NaN", "", "five"] {
+            assert!(gif_seconds(value).is_err(), "accepted {:?}", value);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use glium::Display;
+
+use crate::assets::AssetError;
+use crate::batch::{SpriteBatch, EFFECT_DEPTH};
+use crate::rng::GameRng;
+use crate::shape::Rectangle;
+use crate::sim::Event;
+use crate::texture::{Texture, Transform};
+
+// Slots in the pool. Once they're all alive, new particles take over the
+// oldest ones.
+const POOL_SIZE: usize = 256;
+
+type Color = [f32; 4];
+
+// How one kind of burst looks.
+struct Emitter {
+    count: usize,
+    // in degrees, 0 pointing right and 90 up
+    angle: f32,
+    spread: f32,
+    // pixels per second
+    speed: (f32, f32),
+    // pixels per second squared, pulling down
+    gravity: f32,
+    // seconds
+    lifetime: (f32, f32),
+    // width in pixels at birth and death
+    size: (f32, f32),
+    // colour at birth and death, tinting particles drawn with an image
+    color: (Color, Color),
+    texture: Option<&'static str>,
+}
+
+// Puffs pushed down and back out of the wings.
+const FLAP: Emitter = Emitter {
+    count: 6,
+    angle: 240.0,
+    spread: 50.0,
+    speed: (60.0, 140.0),
+    gravity: -80.0,
+    lifetime: (0.25, 0.45),
+    size: (10.0, 3.0),
+    color: ([1.0, 1.0, 1.0, 0.9], [1.0, 1.0, 1.0, 0.0]),
+    texture: None,
+};
+
+const FISH_SPARKLE: Emitter = Emitter {
+    count: 10,
+    angle: 90.0,
+    spread: 360.0,
+    speed: (80.0, 180.0),
+    gravity: 300.0,
+    lifetime: (0.3, 0.6),
+    size: (6.0, 2.0),
+    color: ([1.0, 0.95, 0.4, 1.0], [0.4, 0.8, 1.0, 0.0]),
+    texture: None,
+};
+
+// The fish itself hops up and fades out.
+const FISH_POP: Emitter = Emitter {
+    count: 1,
+    angle: 90.0,
+    spread: 0.0,
+    speed: (160.0, 160.0),
+    gravity: 400.0,
+    lifetime: (0.4, 0.4),
+    size: (18.0, 12.0),
+    color: ([1.0, 1.0, 1.0, 1.0], [1.0, 1.0, 1.0, 0.0]),
+    texture: Some("./res/platforms/fish.png"),
+};
+
+const STOMP: Emitter = Emitter {
+    count: 14,
+    angle: 90.0,
+    spread: 160.0,
+    speed: (120.0, 260.0),
+    gravity: 600.0,
+    lifetime: (0.3, 0.6),
+    size: (8.0, 4.0),
+    color: ([0.95, 0.85, 0.7, 1.0], [0.6, 0.45, 0.35, 0.0]),
+    texture: None,
+};
+
+const EMITTERS: [&Emitter; 4] = [&FLAP, &FISH_SPARKLE, &FISH_POP, &STOMP];
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[derive(Clone, Copy, Default)]
+struct Particle {
+    // index into EMITTERS
+    emitter: usize,
+    x: f32,
+    y: f32,
+    velocity: [f32; 2],
+    age: f32,
+    lifetime: f32,
+    alive: bool,
+}
+
+pub struct Particles {
+    pool: Vec<Particle>,
+    next: usize,
+    rand: GameRng,
+    quad: Rectangle,
+    // one per emitter, for those drawn with an image
+    textures: Vec<Option<Texture>>,
+}
+
+impl Particles {
+    pub fn new(display: &Display, seed: u64) -> Result<Self, AssetError> {
+        let mut quad = Rectangle::new(1.0, 1.0);
+        quad.set_depth(EFFECT_DEPTH);
+
+        let textures = EMITTERS
+            .iter()
+            .map(|emitter| {
+                emitter
+                    .texture
+                    .map(|path| {
+                        let mut texture = Texture::new(path, display)?;
+                        texture.set_depth(EFFECT_DEPTH);
+                        Ok(texture)
+                    })
+                    .transpose()
+            })
+            .collect::<Result<_, AssetError>>()?;
+
+        Ok(Self {
+            pool: vec![Particle::default(); POOL_SIZE],
+            next: 0,
+            rand: GameRng::new(seed),
+            quad,
+            textures,
+        })
+    }
+
+    pub fn clear(&mut self, seed: u64) {
+        for particle in self.pool.iter_mut() {
+            particle.alive = false;
+        }
+        self.rand = GameRng::new(seed);
+    }
+
+    fn emit(&mut self, emitter: usize, x: f32, y: f32) {
+        let def = EMITTERS[emitter];
+        for _ in 0..def.count {
+            let half = def.spread / 2.0;
+            let angle = (def.angle + self.rand.gen_range(-half..=half)).to_radians();
+            let speed = self.rand.gen_range(def.speed.0..=def.speed.1);
+            let lifetime = self.rand.gen_range(def.lifetime.0..=def.lifetime.1);
+
+            self.pool[self.next] = Particle {
+                emitter,
+                x,
+                y,
+                velocity: [angle.cos() * speed, angle.sin() * speed],
+                age: 0.0,
+                lifetime,
+                alive: true,
+            };
+            self.next = (self.next + 1) % POOL_SIZE;
+        }
+    }
+
+    pub fn react(&mut self, events: &[Event]) {
+        for event in events {
+            match *event {
+                Event::Flap { x, y } => self.emit(0, x, y),
+                Event::Fish { x, y } => {
+                    self.emit(1, x, y);
+                    self.emit(2, x, y);
+                }
+                Event::Stomp { x, y } => self.emit(3, x, y),
+                Event::Death { .. } => {}
+            }
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for particle in self.pool.iter_mut().filter(|particle| particle.alive) {
+            particle.age += dt;
+            if particle.age >= particle.lifetime {
+                particle.alive = false;
+                continue;
+            }
+            particle.velocity[1] -= EMITTERS[particle.emitter].gravity * dt;
+            particle.x += particle.velocity[0] * dt;
+            particle.y += particle.velocity[1] * dt;
+        }
+    }
+
+    pub fn draw(&mut self, batch: &mut SpriteBatch) {
+        for particle in self.pool.iter().filter(|particle| particle.alive) {
+            let def = EMITTERS[particle.emitter];
+            let t = particle.age / particle.lifetime;
+            let size = lerp(def.size.0, def.size.1, t);
+            let color: Color = std::array::from_fn(|i| lerp(def.color.0[i], def.color.1[i], t));
+
+            match self.textures[particle.emitter].as_mut() {
+                Some(texture) => {
+                    texture.scale(size / texture.width);
+                    texture.set_tint([color[0], color[1], color[2]]);
+                    texture.set_alpha(color[3]);
+                    texture.set_position(particle.x, particle.y);
+                    texture.draw(batch);
+                }
+                None => {
+                    self.quad.set_size(size, size);
+                    self.quad.set_color(color);
+                    self.quad.set_position(particle.x, particle.y);
+                    self.quad.draw(batch);
+                }
+            }
+        }
+    }
+}
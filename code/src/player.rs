@@ -1,45 +1,33 @@
-use glium::glutin::event::VirtualKeyCode;
-use glium::{Display, Frame, Program};
+use glium::Display;
 
 use crate::{
-    enemy::Enemy,
-    gui::Topbar,
-    input_mgr::InputManager,
-    shape::{BOTTOM, LEFT},
-    texture::{AnimatedTexture, AnimationMode, Collide, Transform},
+    assets::AssetError,
+    batch::{SpriteBatch, PLAYER_DEPTH},
+    sim::PlayerState,
+    texture::{AnimatedTexture, AnimationMode, Transform},
 };
 
 pub struct Player {
-    pub x: f32,
-    pub y: f32,
-    pub width: f32,
-    pub height: f32,
     pub texture: AnimatedTexture,
     pub death_animation: AnimatedTexture,
-    pub velocity: [f32; 2],
-    pub is_on_platform: bool,
-    pub is_moving_right: bool,
-    pub distance: f32,
-    pub is_dead: bool,
+    is_dead: bool,
 }
 
 impl Player {
-    pub fn new(display: &Display) -> Self {
-        let texture = AnimatedTexture::new(
+    pub fn new(display: &Display) -> Result<Self, AssetError> {
+        let mut texture = AnimatedTexture::new(
             display,
-            vec![
+            &[
                 "./res/player/ubi1.png",
                 "./res/player/ubi2.png",
                 "./res/player/ubi3.png",
-                "./res/player/ubi4.png",
             ],
             0.2,
-            3,
-        );
+        )?;
 
         let mut death = AnimatedTexture::new(
             display,
-            vec![
+            &[
                 "./res/player/ubi_death_1.png",
                 "./res/player/ubi_death_2.png",
                 "./res/player/ubi_death_3.png",
@@ -47,117 +35,55 @@ impl Player {
                 "./res/player/ubi_death_5.png",
             ],
             0.15,
-            5,
-        );
+        )?;
         death.set_mode(AnimationMode::Once);
 
-        let (width, height) = texture.get_dimensions();
-        Player {
-            texture: texture,
+        texture.set_depth(PLAYER_DEPTH);
+        death.set_depth(PLAYER_DEPTH);
+
+        Ok(Player {
+            texture,
             death_animation: death,
-            x: BOTTOM + 48.0,
-            y: 120.0,
-            width: width,
-            height: height,
-            velocity: [0.0, 0.0],
-            is_on_platform: false,
-            is_moving_right: false,
-            distance: 0.0,
             is_dead: false,
-        }
-    }
-
-    pub fn set_on_platform(&mut self, b: bool) {
-        self.is_on_platform = b;
-    }
-
-    pub fn is_dead(&self) -> bool {
-        self.is_dead
-    }
-
-    pub fn set_dead(&mut self, b: bool) {
-        self.is_dead = b;
-    }
-
-    pub fn reset(&mut self) {
-        self.set_dead(false);
-        self.x = BOTTOM + 48.0;
-        self.y = 120.0;
-        self.velocity = [0.0, 0.0];
+        })
     }
 
-    pub fn apply_gravity(&mut self, dt: f32) {
-        self.y += self.velocity[1];
-        if self.y + self.velocity[1] >= BOTTOM - self.height {
-            self.velocity[1] -= 3.0 * dt;
-        } else {
-            self.velocity[1] = 0.0;
+    pub fn update(&mut self, state: &PlayerState, dt: f32) {
+        self.texture.update(dt);
+        if state.is_dead() {
+            if !self.is_dead {
+                self.death_animation.restart();
+            }
+            self.death_animation.update(dt);
         }
-        self.texture.set_position(self.x, self.y);
-        self.death_animation.set_position(self.x, self.y);
+        self.is_dead = state.is_dead();
     }
 
-    pub fn was_killed(&mut self, enemy: &Enemy) -> bool {
-        (self.texture.collide_left(&enemy.texture) || self.texture.collide_right(&enemy.texture))
-            && !enemy.is_dead()
+    pub fn set_alpha(&mut self, alpha: f32) {
+        self.texture.set_alpha(alpha);
+        self.death_animation.set_alpha(alpha);
     }
 
-    pub fn check_interaction(&mut self, enemy: &mut Enemy, topbar: &mut Topbar, display: &Display) {
-        if self.texture.collide_bottom(&enemy.texture) && !self.is_dead && !enemy.is_dead() {
-            topbar.increment_enemy_count(display);
-            enemy.set_dead(true);
-        } else if self.was_killed(&enemy) {
-            self.set_dead(true);
-        }
+    pub fn set_hue_shift(&mut self, degrees: f32) {
+        self.texture.set_hue_shift(degrees);
+        self.death_animation.set_hue_shift(degrees);
     }
 
-    pub fn update(&mut self, input: &mut InputManager, dt: f32) {
-        self.texture.update(dt);
-        if self.is_dead() {
-            self.is_moving_right = false;
-            self.death_animation.update(dt);
-            self.apply_gravity(dt);
-            return;
-        }
-
-        if self.y < BOTTOM - self.height / 2.0 {
-            self.set_dead(true);
-            return;
-        }
-
-        self.x += self.velocity[0];
-        self.apply_gravity(dt);
-
-        if input.key_down(VirtualKeyCode::Up) {
-            self.velocity[1] = 380.0 * dt;
-        }
-        if input.key_down(VirtualKeyCode::Right) {
-            self.x += 200.0 * dt;
-            self.distance += dt;
-            self.is_moving_right = true;
-        } else {
-            self.is_moving_right = false;
-        }
-        if input.key_down(VirtualKeyCode::Left) && self.is_on_platform {
-            self.x -= 200.0 * dt;
-            self.distance -= dt;
-        }
-
-        if self.x >= 0.0 {
-            self.x = 0.0;
-        } else if self.x <= LEFT + self.width / 2. {
-            self.x = LEFT + self.width / 2.;
-        }
+    pub fn set_depth(&mut self, depth: i32) {
+        self.texture.set_depth(depth);
+        self.death_animation.set_depth(depth);
+    }
 
-        self.texture.set_position(self.x, self.y);
-        self.death_animation.set_position(self.x, self.y);
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.texture.set_position(x, y);
+        self.death_animation.set_position(x, y);
     }
 
-    pub fn draw(&mut self, target: &mut Frame, program: &Program) {
+    pub fn draw(&mut self, batch: &mut SpriteBatch) {
         if self.is_dead {
-            self.death_animation.draw(target, program)
+            self.death_animation.draw(batch)
         } else {
-            self.texture.draw(target, program)
+            self.texture.draw(batch)
         };
     }
 }
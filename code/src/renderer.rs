@@ -0,0 +1,150 @@
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::glutin::window::Fullscreen;
+use glium::index::{NoIndices, PrimitiveType};
+use glium::texture::{MipmapsOption, RawImage2d, SrgbFormat, SrgbTexture2d};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
+use glium::{uniform, Display, Program, Rect, Surface, VertexBuffer};
+use image::RgbaImage;
+
+use crate::batch::SpriteBatch;
+use crate::shape::{Rectangle, Vertex, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Scaling {
+    // whole multiples only, so every game pixel is the same size on screen
+    Integer,
+    // as large as fits
+    #[default]
+    Aspect,
+}
+
+impl Scaling {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "integer" => Some(Scaling::Integer),
+            "aspect" => Some(Scaling::Aspect),
+            _ => None,
+        }
+    }
+}
+
+// The game always draws into a SCREEN_WIDTH x SCREEN_HEIGHT canvas; the
+// renderer then scales that onto the window with nearest-neighbour sampling,
+// so pixel art stays crisp, with black bars on the sides that don't fill.
+pub struct Renderer {
+    canvas: SrgbTexture2d,
+    quad: VertexBuffer<Vertex>,
+    scaling: Scaling,
+    fullscreen: bool,
+}
+
+impl Renderer {
+    pub fn new(display: &Display, scaling: Scaling, fullscreen: bool) -> Self {
+        let canvas = SrgbTexture2d::empty_with_format(
+            display,
+            SrgbFormat::U8U8U8U8,
+            MipmapsOption::NoMipmap,
+            SCREEN_WIDTH as u32,
+            SCREEN_HEIGHT as u32,
+        )
+        .unwrap();
+
+        let vertices = Rectangle::new(SCREEN_WIDTH, SCREEN_HEIGHT)
+            .vertices()
+            .map(|vertex| Vertex {
+                color: [1.0; 4],
+                ..vertex
+            });
+
+        let mut renderer = Self {
+            canvas,
+            quad: VertexBuffer::new(display, &vertices).unwrap(),
+            scaling,
+            fullscreen: false,
+        };
+        if fullscreen {
+            renderer.toggle_fullscreen(display);
+        }
+        renderer
+    }
+
+    pub fn toggle_fullscreen(&mut self, display: &Display) {
+        self.fullscreen = !self.fullscreen;
+        let fullscreen = self.fullscreen.then_some(Fullscreen::Borderless(None));
+        display.gl_window().window().set_fullscreen(fullscreen);
+    }
+
+    // Everything `draw` pushes lands on the canvas first, which is the one
+    // place to add post-processing passes or read back a finished frame.
+    pub fn render(
+        &mut self,
+        display: &Display,
+        program: &Program,
+        batch: &mut SpriteBatch,
+        draw: impl FnOnce(&mut SpriteBatch),
+    ) {
+        let mut canvas = SimpleFrameBuffer::new(display, &self.canvas).unwrap();
+        canvas.clear_color(1.0, 1.0, 1.0, 1.0);
+        draw(batch);
+        let (width, height) = self.canvas.dimensions();
+        let whole = Rect {
+            left: 0,
+            bottom: 0,
+            width,
+            height,
+        };
+        batch.flush(&mut canvas, program, whole);
+
+        let mut target = display.draw();
+        let viewport = self.viewport(target.get_dimensions());
+        target.clear_color(0.0, 0.0, 0.0, 1.0);
+
+        let uniforms = uniform! {
+            isTex: true,
+            tex: self.canvas
+                .sampled()
+                .magnify_filter(MagnifySamplerFilter::Nearest)
+                .minify_filter(MinifySamplerFilter::Nearest),
+        };
+        let params = glium::DrawParameters {
+            viewport: Some(viewport),
+            ..Default::default()
+        };
+        target
+            .draw(
+                &self.quad,
+                NoIndices(PrimitiveType::TriangleStrip),
+                program,
+                &uniforms,
+                &params,
+            )
+            .unwrap();
+        target.finish().unwrap();
+    }
+
+    // The canvas as last drawn, top row first.
+    pub fn frame(&self) -> RgbaImage {
+        let raw: RawImage2d<u8> = self.canvas.read();
+        let image = RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned())
+            .expect("canvas read back at the wrong size");
+        image::imageops::flip_vertical(&image)
+    }
+
+    fn viewport(&self, (width, height): (u32, u32)) -> Rect {
+        let fit = (width as f32 / SCREEN_WIDTH).min(height as f32 / SCREEN_HEIGHT);
+        // a window smaller than the game can't take an integer scale
+        let scale = match self.scaling {
+            Scaling::Integer if fit >= 1.0 => fit.floor(),
+            _ => fit,
+        };
+
+        let view_width = (SCREEN_WIDTH * scale) as u32;
+        let view_height = (SCREEN_HEIGHT * scale) as u32;
+        Rect {
+            left: width.saturating_sub(view_width) / 2,
+            bottom: height.saturating_sub(view_height) / 2,
+            width: view_width,
+            height: view_height,
+        }
+    }
+}
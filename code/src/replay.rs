@@ -0,0 +1,401 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use crate::sim::{Input, Scores, World, FIXED_DT};
+
+pub const REPLAY_DIR: &str = "replays";
+pub const LAST_REPLAY: &str = "last.ubr";
+pub const BEST_REPLAY: &str = "best.ubr";
+
+const MAGIC: &[u8; 4] = b"UBIR";
+// Bumped whenever the rules change so that older recordings would play out
+// differently.
+const VERSION: u8 = 8;
+// Two hours of steps. Loading the best run re-simulates all of it before the
+// window opens, so a corrupt length mustn't be able to stall startup.
+const MAX_LENGTH: u32 = 2 * 60 * 60 * 120;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Key {
+    Flap,
+    Left,
+    Right,
+}
+
+impl Key {
+    const ALL: [Key; 3] = [Key::Flap, Key::Left, Key::Right];
+
+    fn is_down(&self, input: &Input) -> bool {
+        match self {
+            Key::Flap => input.flap,
+            Key::Left => input.left,
+            Key::Right => input.right,
+        }
+    }
+
+    fn set(&self, input: &mut Input, down: bool) {
+        match self {
+            Key::Flap => input.flap = down,
+            Key::Left => input.left = down,
+            Key::Right => input.right = down,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct KeyEvent {
+    pub tick: u32,
+    pub key: Key,
+    pub pressed: bool,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Replay {
+    pub seed: u64,
+    pub length: u32,
+    pub events: Vec<KeyEvent>,
+}
+
+// File layout, little endian:
+//   "UBIR" version:u8 seed:u64 length:u32 count:u32
+//   count * (tick delta:LEB128, key << 1 | pressed:u8)
+impl Replay {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            length: 0,
+            events: vec![],
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Replay::decode(&fs::read(path)?)
+    }
+
+    fn decode(data: &[u8]) -> io::Result<Self> {
+        let mut reader = data;
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a replay file"));
+        }
+        let version = read_u8(&mut reader)?;
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported replay version {}", version)));
+        }
+
+        let mut replay = Replay::new(u64::from_le_bytes(read_array(&mut reader)?));
+        replay.length = u32::from_le_bytes(read_array(&mut reader)?);
+        let count = u32::from_le_bytes(read_array(&mut reader)?);
+
+        let mut tick: u32 = 0;
+        for _ in 0..count {
+            tick = tick
+                .checked_add(read_varint(&mut reader)?)
+                .ok_or_else(|| invalid("tick overflow"))?;
+            let byte = read_u8(&mut reader)?;
+            let key = *Key::ALL
+                .get((byte >> 1) as usize)
+                .ok_or_else(|| invalid("unknown key"))?;
+            replay.events.push(KeyEvent {
+                tick,
+                key,
+                pressed: byte & 1 == 1,
+            });
+        }
+
+        // every event happens during the run
+        if replay.length > MAX_LENGTH || (count > 0 && replay.length <= tick) {
+            return Err(invalid(&format!("invalid replay length {}", replay.length)));
+        }
+
+        Ok(replay)
+    }
+
+    // Runs the recording headless to find out how it scored.
+    pub fn simulate(&self) -> Scores {
+        let mut world = World::new(self.seed);
+        let mut playback = Playback::new(self.clone());
+        while world.tick < self.length.min(MAX_LENGTH) {
+            let input = playback.input_at(world.tick);
+            world.update(&input, FIXED_DT);
+        }
+        world.scores
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.encode())
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut data: Vec<u8> = vec![];
+        data.extend_from_slice(MAGIC);
+        data.push(VERSION);
+        data.extend_from_slice(&self.seed.to_le_bytes());
+        data.extend_from_slice(&self.length.to_le_bytes());
+        data.extend_from_slice(&(self.events.len() as u32).to_le_bytes());
+
+        let mut tick = 0;
+        for event in self.events.iter() {
+            write_varint(&mut data, event.tick - tick);
+            data.push((event.key as u8) << 1 | event.pressed as u8);
+            tick = event.tick;
+        }
+        data
+    }
+}
+
+pub struct Recorder {
+    replay: Replay,
+    last: Input,
+}
+
+impl Recorder {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            replay: Replay::new(seed),
+            last: Input::default(),
+        }
+    }
+
+    // The simulation only ever sees one snapshot per tick, so the changes
+    // between consecutive snapshots are all a replay needs to reproduce it.
+    pub fn record(&mut self, tick: u32, input: &Input) {
+        for key in Key::ALL {
+            let down = key.is_down(input);
+            if down != key.is_down(&self.last) {
+                self.replay.events.push(KeyEvent {
+                    tick,
+                    key,
+                    pressed: down,
+                });
+            }
+        }
+        self.last = *input;
+        self.replay.length = tick + 1;
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+}
+
+pub struct Playback {
+    replay: Replay,
+    cursor: usize,
+    input: Input,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            cursor: 0,
+            input: Input::default(),
+        }
+    }
+
+    pub fn input_at(&mut self, tick: u32) -> Input {
+        while let Some(event) = self.replay.events.get(self.cursor) {
+            if event.tick > tick {
+                break;
+            }
+            event.key.set(&mut self.input, event.pressed);
+            self.cursor += 1;
+        }
+        self.input
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_u8(reader: &mut &[u8]) -> io::Result<u8> {
+    let [byte] = read_array(reader)?;
+    Ok(byte)
+}
+
+fn read_array<const N: usize>(reader: &mut &[u8]) -> io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_varint(reader: &mut &[u8]) -> io::Result<u32> {
+    let mut value: u32 = 0;
+    for shift in (0..35).step_by(7) {
+        let byte = read_u8(reader)?;
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("varint too long"))
+}
+
+fn write_varint(data: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        data.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::testing::scripted;
+
+    fn record(seed: u64, ticks: u32) -> (Recorder, Scores) {
+        let mut world = World::new(seed);
+        let mut recorder = Recorder::new(seed);
+        for tick in 0..ticks {
+            let input = scripted(tick);
+            recorder.record(world.tick, &input);
+            world.update(&input, FIXED_DT);
+        }
+        (recorder, world.scores)
+    }
+
+    fn varint(value: u32) -> Vec<u8> {
+        let mut data = vec![];
+        write_varint(&mut data, value);
+        data
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        assert_eq!(varint(0), [0x00]);
+        assert_eq!(varint(0x7f), [0x7f]);
+        assert_eq!(varint(0x80), [0x80, 0x01]);
+        assert_eq!(varint(u32::MAX), [0xff, 0xff, 0xff, 0xff, 0x0f]);
+        for value in [0, 1, 0x7f, 0x80, 0x3fff, 0x4000, u32::MAX] {
+            let data = varint(value);
+            let mut reader = data.as_slice();
+            assert_eq!(read_varint(&mut reader).unwrap(), value);
+            assert!(reader.is_empty());
+        }
+    }
+
+    #[test]
+    fn overlong_varint_is_invalid() {
+        let mut reader: &[u8] = &[0x80; 6];
+        let error = read_varint(&mut reader).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let (recorder, _) = record(321, 1500);
+        let replay = recorder.replay();
+        assert!(!replay.events.is_empty());
+
+        let path = std::env::temp_dir().join(format!("ubiland-test-{}.ubr", std::process::id()));
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.seed, replay.seed);
+        assert_eq!(loaded.length, replay.length);
+        assert_eq!(loaded.events, replay.events);
+    }
+
+    #[test]
+    fn wrong_magic_is_invalid() {
+        let mut data = Replay::new(1).encode();
+        data[0] = b'X';
+        let error = Replay::decode(&data).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn wrong_version_is_invalid() {
+        let mut data = Replay::new(1).encode();
+        data[MAGIC.len()] = VERSION + 1;
+        let error = Replay::decode(&data).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn unknown_key_is_invalid() {
+        let mut replay = Replay::new(1);
+        replay.events.push(KeyEvent {
+            tick: 3,
+            key: Key::Right,
+            pressed: true,
+        });
+        let mut data = replay.encode();
+        *data.last_mut().unwrap() = 7 << 1;
+        let error = Replay::decode(&data).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn length_must_cover_the_events() {
+        let mut replay = Replay::new(1);
+        replay.events.push(KeyEvent {
+            tick: 30,
+            key: Key::Flap,
+            pressed: true,
+        });
+        for (length, valid) in [(0, false), (30, false), (31, true), (MAX_LENGTH, true)] {
+            replay.length = length;
+            let decoded = Replay::decode(&replay.encode());
+            assert_eq!(decoded.is_ok(), valid, "length {}", length);
+        }
+    }
+
+    #[test]
+    fn overlong_length_is_invalid() {
+        let mut replay = Replay::new(1);
+        replay.length = u32::MAX;
+        let error = Replay::decode(&replay.encode()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        replay.length = MAX_LENGTH + 1;
+        assert!(Replay::decode(&replay.encode()).is_err());
+    }
+
+    #[test]
+    fn truncated_file_is_unexpected_eof() {
+        let (recorder, _) = record(5, 600);
+        let data = recorder.replay().encode();
+        for length in 0..data.len() {
+            let error = Replay::decode(&data[..length]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof, "at {}", length);
+        }
+        assert!(Replay::decode(&data).is_ok());
+    }
+
+    #[test]
+    fn playback_gives_back_the_recorded_inputs() {
+        let mut recorder = Recorder::new(0);
+        for tick in 0..2000 {
+            recorder.record(tick, &scripted(tick));
+        }
+        let mut playback = Playback::new(recorder.replay().clone());
+        for tick in 0..2000 {
+            assert_eq!(playback.input_at(tick), scripted(tick), "at tick {}", tick);
+        }
+    }
+
+    // The best run's score is re-simulated from its file, so it has to come
+    // out the same as it did live.
+    #[test]
+    fn simulate_matches_the_live_run() {
+        let (recorder, scores) = record(2024, 4000);
+        assert!(scores.distance > 0);
+
+        let replay = Replay::decode(&recorder.replay().encode()).unwrap();
+        assert_eq!(replay.simulate(), scores);
+    }
+}
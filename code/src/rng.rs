@@ -0,0 +1,148 @@
+use std::ops::{Range, RangeInclusive};
+
+use rand::Rng;
+
+// Seeds are kept short enough to read off the game over screen and type
+// back in with --seed.
+const SEED_LIMIT: u64 = 1_000_000;
+
+pub const BACKGROUND_STREAM: u64 = 1;
+pub const WEATHER_STREAM: u64 = 16;
+pub const LEVEL_STREAM: u64 = 32;
+pub const ENEMY_STREAM: u64 = 48;
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// The splitmix64 finaliser.
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// Ranges the game picks numbers from. Sampling is done here rather than by
+// rand, whose generators and distributions are free to change output between
+// releases; a seed has to lay out the same level for as long as replays of
+// it are around.
+pub trait Pick {
+    type Output;
+
+    fn pick(self, bits: u64) -> Self::Output;
+}
+
+impl Pick for Range<f32> {
+    type Output = f32;
+
+    // 24 bits fill an f32 mantissa, giving [0, 1)
+    fn pick(self, bits: u64) -> f32 {
+        let unit = (bits >> 40) as f32 / (1u64 << 24) as f32;
+        let value = self.start + (self.end - self.start) * unit;
+        // rounding can land on the end, which is excluded
+        if value < self.end {
+            value
+        } else {
+            self.start
+        }
+    }
+}
+
+impl Pick for RangeInclusive<f32> {
+    type Output = f32;
+
+    fn pick(self, bits: u64) -> f32 {
+        let (start, end) = self.into_inner();
+        let unit = (bits >> 40) as f32 / ((1u64 << 24) - 1) as f32;
+        (start + (end - start) * unit).min(end)
+    }
+}
+
+impl Pick for Range<i32> {
+    type Output = i32;
+
+    // scales the top 32 bits onto the range instead of taking a remainder,
+    // which would favour the low end
+    fn pick(self, bits: u64) -> i32 {
+        let span = (self.end as i64 - self.start as i64) as u64;
+        (self.start as i64 + (((bits >> 32) * span) >> 32) as i64) as i32
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn random_seed() -> u64 {
+        rand::thread_rng().gen_range(0..SEED_LIMIT)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        mix(self.state)
+    }
+
+    pub fn gen_range<R: Pick>(&mut self, range: R) -> R::Output {
+        let bits = self.next_u64();
+        range.pick(bits)
+    }
+}
+
+// A stateless roll for things that must give the same answer every time
+// they're asked, such as which decoration a background tile shows when it
+// scrolls back into view. Each system rolls on its own stream of the run
+// seed, so the background never shifts the level layout.
+pub fn roll(seed: u64, stream: u64, index: i64) -> u64 {
+    mix((seed ^ stream.wrapping_mul(GOLDEN_GAMMA))
+        .wrapping_add((index as u64).wrapping_mul(GOLDEN_GAMMA))
+        .wrapping_add(GOLDEN_GAMMA))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The reference splitmix64 sequence. If this changes, so does every
+    // level and every saved replay.
+    #[test]
+    fn sequence_is_splitmix64() {
+        let mut rng = GameRng::new(1234567);
+        let expected = [
+            6457827717110365317,
+            3203168211198807973,
+            9817491932198370423,
+            4593380528125082431,
+            16408922859458223821,
+        ];
+        for value in expected {
+            assert_eq!(rng.next_u64(), value);
+        }
+    }
+
+    #[test]
+    fn picks_stay_in_range() {
+        let mut rng = GameRng::new(9);
+        for _ in 0..10_000 {
+            let x = rng.gen_range(-3.5..2.0);
+            assert!((-3.5..2.0).contains(&x));
+            let y = rng.gen_range(1.0..=1.5);
+            assert!((1.0..=1.5).contains(&y));
+            let n = rng.gen_range(-2..10);
+            assert!((-2..10).contains(&n));
+        }
+        assert!(Pick::pick(0.0..1.0, u64::MAX) < 1.0);
+        assert_eq!(Pick::pick(1.0..=2.0, u64::MAX), 2.0);
+        assert_eq!(Pick::pick(0..10, 0), 0);
+        assert_eq!(Pick::pick(0..10, u64::MAX), 9);
+    }
+
+    #[test]
+    fn rolls_differ_by_stream_and_index() {
+        assert_ne!(roll(7, 3, 11), roll(7, 4, 11));
+        assert_ne!(roll(7, 3, 11), roll(7, 3, 12));
+    }
+}
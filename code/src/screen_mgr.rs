@@ -1,10 +1,16 @@
 use glium::glutin::event::VirtualKeyCode;
-use glium::{Display, Frame, Program};
+use glium::Display;
 
+use crate::assets::AssetError;
 use crate::background::Background;
+use crate::batch::{SpriteBatch, BACKGROUND_LAYER, WORLD_LAYER};
 use crate::game::Level;
 use crate::input_mgr::InputManager;
+use crate::options::Options;
+use crate::replay::{Replay, BEST_REPLAY, LAST_REPLAY, REPLAY_DIR};
+use crate::rng::GameRng;
 use crate::screens::{GameOver, Pause, Screen, StartScreen, Store};
+use std::path::Path;
 
 pub struct ScreenMgr {
     pub level: Level,
@@ -15,59 +21,154 @@ pub struct ScreenMgr {
     pub input: InputManager,
     current_screen: Screen,
     background: Background,
+    fixed_seed: Option<u64>,
+    replay: Option<Replay>,
+    best: Option<(Replay, u32)>,
+    chase_best: bool,
     exit: bool,
 }
 
+// An explicit seed wins; otherwise chasing the ghost means replaying the
+// layout the best run was recorded on.
+fn next_seed(fixed_seed: Option<u64>, chase_best: bool, best: &Option<(Replay, u32)>) -> u64 {
+    match (fixed_seed, best) {
+        (Some(seed), _) => seed,
+        (None, Some((replay, _))) if chase_best => replay.seed,
+        _ => GameRng::random_seed(),
+    }
+}
+
+// The best run is re-simulated on load rather than trusting a stored score,
+// so a replay from an older build can't claim a distance it no longer reaches.
+fn load_best() -> Option<(Replay, u32)> {
+    let replay = Replay::load(&Path::new(REPLAY_DIR).join(BEST_REPLAY)).ok()?;
+    let distance = replay.simulate().distance;
+    Some((replay, distance))
+}
+
 impl ScreenMgr {
-    pub fn new(display: &Display) -> Self {
-        let level = Level::new(display);
-        let start = StartScreen::new(display);
+    pub fn new(
+        display: &Display,
+        options: Options,
+        replay: Option<Replay>,
+    ) -> Result<Self, AssetError> {
+        let best = if replay.is_none() { load_best() } else { None };
+        let seed = match &replay {
+            Some(replay) => replay.seed,
+            None => next_seed(options.seed, options.ghost, &best),
+        };
+        let mut level = Level::new(display, seed, options.effects)?;
+        let start = StartScreen::new(display)?;
         let input = InputManager::new();
-        let background = Background::new(display);
+        let background = Background::new(display, seed, options.weather)?;
 
-        ScreenMgr {
-            level: level,
-            start: start,
-            game_over: GameOver::new(display),
-            pause: Pause::new(display),
-            store: Store::new(display),
-            input: input,
-            current_screen: Screen::Start,
-            background: background,
-            exit: false,
+        // replays skip the start menu and go straight into the recorded run
+        let mut current_screen = Screen::Start;
+        if let Some(replay) = &replay {
+            level.play_back(display, replay.clone())?;
+            current_screen = Screen::Play;
+        } else {
+            level.race(display, best.as_ref().map(|(replay, _)| replay))?;
         }
+
+        Ok(ScreenMgr {
+            level,
+            start,
+            game_over: GameOver::new(display)?,
+            pause: Pause::new(display)?,
+            store: Store::new(display)?,
+            input,
+            current_screen,
+            background,
+            fixed_seed: options.seed,
+            replay,
+            best,
+            chase_best: options.ghost,
+            exit: false,
+        })
     }
 
     pub fn exited(&self) -> bool {
         self.exit
     }
 
-    pub fn update(&mut self, display: &Display, dt: f32) {
-        self.background.update(dt);
+    fn restart_level(&mut self, display: &Display) -> Result<(), AssetError> {
+        match &self.replay {
+            Some(replay) => {
+                self.background.reseed(replay.seed);
+                self.level.play_back(display, replay.clone())
+            }
+            None => {
+                let seed = next_seed(self.fixed_seed, self.chase_best, &self.best);
+                self.background.reseed(seed);
+                self.level.restart(display, seed)?;
+                self.level
+                    .race(display, self.best.as_ref().map(|(replay, _)| replay))
+            }
+        }
+    }
+
+    // Returns whether the run beat the best one.
+    fn save_replay(&mut self) -> bool {
+        if self.replay.is_some() {
+            return false;
+        }
+        let replay = self.level.replay().clone();
+        let path = Path::new(REPLAY_DIR).join(LAST_REPLAY);
+        if let Err(e) = replay.save(&path) {
+            eprintln!("Unable to save replay to {}: {}", path.display(), e);
+        }
+
+        let distance = self.level.scores().distance;
+        if self.best.as_ref().is_none_or(|(_, best)| distance > *best) {
+            let path = Path::new(REPLAY_DIR).join(BEST_REPLAY);
+            if let Err(e) = replay.save(&path) {
+                eprintln!("Unable to save replay to {}: {}", path.display(), e);
+            }
+            self.best = Some((replay, distance));
+            return true;
+        }
+        false
+    }
+
+    pub fn update(&mut self, display: &Display, dt: f32) -> Result<(), AssetError> {
+        self.background.update(dt, self.level.scores().distance);
         match self.current_screen {
             Screen::Start => {
                 self.start.update(&mut self.input, dt);
                 if self.start.menu_choice == 0 && self.input.key_went_up(VirtualKeyCode::Return) {
                     self.current_screen = Screen::Play;
-                } else if self.start.menu_choice == 1 && self.input.key_went_up(VirtualKeyCode::Return) {
+                } else if self.start.menu_choice == 1
+                    && self.input.key_went_up(VirtualKeyCode::Return)
+                {
                     self.current_screen = Screen::Store;
-                }else if self.start.menu_choice == 2 && self.input.key_went_up(VirtualKeyCode::Return) {
-                    self.exit=true;
+                } else if self.start.menu_choice == 2
+                    && self.input.key_went_up(VirtualKeyCode::Return)
+                {
+                    self.exit = true;
                 }
             }
-            Screen::Store=>{
+            Screen::Store => {
                 self.store.update(&mut self.input, dt);
-                if self.store.exited(){
-                    self.current_screen=Screen::Start;
+                if self.store.exited() {
+                    self.current_screen = Screen::Start;
                 }
             }
             Screen::Play => {
-                self.level.update(&mut self.input, display, dt);
+                self.level.update(&mut self.input, display, dt)?;
                 if self.level.game_over(dt) {
                     self.current_screen = Screen::GameOver;
+                    let is_best = self.save_replay();
+                    self.game_over.set_run(
+                        self.level.seed(),
+                        self.level.scores().distance,
+                        self.best.as_ref().map(|(_, best)| *best),
+                        is_best,
+                    );
                 }
                 if self.level.paused() {
                     self.current_screen = Screen::Pause;
+                    self.pause.set_seed(self.level.seed());
                 }
             }
             Screen::GameOver => {
@@ -75,7 +176,7 @@ impl ScreenMgr {
                 if self.game_over.menu_choice == 0 && self.input.key_went_up(VirtualKeyCode::Return)
                 {
                     self.current_screen = Screen::Play;
-                    self.level.restart(display);
+                    self.restart_level(display)?;
                 } else if self.game_over.menu_choice == 1
                     && self.input.key_went_up(VirtualKeyCode::Return)
                 {
@@ -95,7 +196,7 @@ impl ScreenMgr {
                     && self.input.key_went_up(VirtualKeyCode::Return)
                 {
                     self.current_screen = Screen::Play;
-                    self.level.restart(display);
+                    self.restart_level(display)?;
                     self.level.resume();
                 } else if self.pause.menu_choice == 2
                     && self.input.key_went_up(VirtualKeyCode::Return)
@@ -109,25 +210,30 @@ impl ScreenMgr {
                 }
             }
         }
+        Ok(())
     }
 
-    pub fn draw(&mut self, target: &mut Frame, program: &Program) {
-        self.background.draw(target, program);
+    pub fn draw(&mut self, batch: &mut SpriteBatch, alpha: f32) {
+        batch.set_layer(BACKGROUND_LAYER);
+        batch.set_offset(0.0, 0.0);
+        self.background.draw(batch, self.level.camera_x(alpha));
+
+        batch.set_layer(WORLD_LAYER);
         match self.current_screen {
             Screen::Start => {
-                self.start.draw(target, program);
+                self.start.draw(batch);
             }
-            Screen::Store=>{
-                self.store.draw(target, program);
+            Screen::Store => {
+                self.store.draw(batch);
             }
             Screen::Play => {
-                self.level.draw(target, program);
+                self.level.draw(batch, alpha);
             }
             Screen::GameOver => {
-                self.game_over.draw(target, program);
+                self.game_over.draw(batch);
             }
             Screen::Pause => {
-                self.pause.draw(target, program);
+                self.pause.draw(batch);
             }
         }
     }
@@ -1,11 +1,22 @@
 use glium::glutin::event::VirtualKeyCode;
-use glium::{Display, Frame, Program};
+use glium::Display;
 
+use crate::assets::AssetError;
+use crate::batch::{SpriteBatch, LABEL_DEPTH};
+use crate::gui::NumberDisplay;
 use crate::input_mgr::InputManager;
-use crate::platform::{Platform, Size};
+use crate::platform::Platform;
 use crate::shape::SCREEN_WIDTH;
+use crate::sim::{PlatformState, Size};
+use crate::text::{Align, Text};
 use crate::texture::{AnimatedTexture, Texture, Transform};
 
+// The yellow of the menu lettering.
+const TEXT_COLOR: [f32; 4] = [1.0, 0.9, 0.35, 1.0];
+
+// Random seeds are under a million, so they all show as six digits.
+const SEED_DIGITS: usize = 6;
+
 pub enum Screen {
     Start,
     Play,
@@ -21,54 +32,53 @@ pub struct StartScreen {
     platform: Platform,
     platform2: Platform,
     ubi: AnimatedTexture,
-    pub started: bool,
     pub menu_choice: i8,
     elapsed_time: f32,
 }
 
 impl StartScreen {
-    pub fn new(display: &Display) -> Self {
-        let mut logo = Texture::new("./res/gui/logo.png", display);
+    pub fn new(display: &Display) -> Result<Self, AssetError> {
+        let mut logo = Texture::new("./res/gui/logo.png", display)?;
         logo.scale(1.2);
         logo.set_position(SCREEN_WIDTH / 3. - logo.width / 2. + 50., 70.);
 
-        let mut menu = Texture::new("./res/gui/start_menu.png", display);
+        let mut menu = Texture::new("./res/gui/start_menu.png", display)?;
         menu.set_position(160.0, -85.0);
 
-        let mut cursor = Texture::new("./res/gui/cursor.png", display);
+        let mut cursor = Texture::new("./res/gui/cursor.png", display)?;
         cursor.set_position(SCREEN_WIDTH / 3. - cursor.width / 2. + 5.0, -45.0);
 
-        let mut platform = Platform::new(display, Size::Medium);
+        let mut platform = PlatformState::new(Size::Medium);
         platform.set_position(-170.0, -60.0);
+        let platform = Platform::new(display, &platform)?;
 
-        let mut platform2 = Platform::new(display, Size::Large);
+        let mut platform2 = PlatformState::new(Size::Large);
         platform2.set_position(-350.0, -200.0);
+        let platform2 = Platform::new(display, &platform2)?;
 
         let mut ubi = AnimatedTexture::new(
             display,
-            vec![
+            &[
                 "./res/player/loaf1.png",
                 "./res/player/loaf2.png",
                 "./res/player/loaf3.png",
                 "./res/player/loaf4.png",
             ],
             0.2,
-            4,
-        );
+        )?;
         let h = ubi.height / 2.0;
         ubi.set_position(-180.0, -12.0 + h);
 
-        StartScreen {
-            logo: logo,
-            started: false,
-            cursor: cursor,
-            platform: platform,
-            platform2: platform2,
-            ubi: ubi,
-            menu: menu,
+        Ok(StartScreen {
+            logo,
+            cursor,
+            platform,
+            platform2,
+            ubi,
+            menu,
             menu_choice: 0,
             elapsed_time: 0.0,
-        }
+        })
     }
 
     pub fn update(&mut self, input: &mut InputManager, dt: f32) {
@@ -95,13 +105,13 @@ impl StartScreen {
         }
     }
 
-    pub fn draw(&mut self, target: &mut Frame, program: &Program) {
-        self.platform2.draw(target, program);
-        self.logo.draw(target, program);
-        self.menu.draw(target, program);
-        self.cursor.draw(target, program);
-        self.platform.draw(target, program);
-        self.ubi.draw(target, program);
+    pub fn draw(&mut self, batch: &mut SpriteBatch) {
+        self.platform2.draw(batch);
+        self.logo.draw(batch);
+        self.menu.draw(batch);
+        self.cursor.draw(batch);
+        self.platform.draw(batch);
+        self.ubi.draw(batch);
     }
 }
 
@@ -113,13 +123,13 @@ pub struct Store {
 }
 
 impl Store {
-    pub fn new(display: &Display) -> Self {
-        Self {
-            panel: Texture::new("./res/gui/panel.png", display),
-            coming_soon: Texture::new("./res/gui/coming_soon.png", display),
+    pub fn new(display: &Display) -> Result<Self, AssetError> {
+        Ok(Self {
+            panel: Texture::new("./res/gui/panel.png", display)?,
+            coming_soon: Texture::new("./res/gui/coming_soon.png", display)?,
             elapsed_time: 0.0,
             exited: false,
-        }
+        })
     }
 
     pub fn exited(&mut self) -> bool {
@@ -145,9 +155,9 @@ impl Store {
         self.coming_soon.translate(0.0, y);
     }
 
-    pub fn draw(&mut self, target: &mut Frame, program: &Program) {
-        self.panel.draw(target, program);
-        self.coming_soon.draw(target, program);
+    pub fn draw(&mut self, batch: &mut SpriteBatch) {
+        self.panel.draw(batch);
+        self.coming_soon.draw(batch);
     }
 }
 
@@ -157,29 +167,47 @@ pub struct Pause {
     panel: Texture,
     pub menu_choice: i8,
     title: Texture,
+    seed: Text,
     elapsed_time: f32,
 }
 
 impl Pause {
-    pub fn new(display: &Display) -> Self {
-        let mut menu = Texture::new("./res/gui/pause_menu.png", display);
+    pub fn new(display: &Display) -> Result<Self, AssetError> {
+        let mut menu = Texture::new("./res/gui/pause_menu.png", display)?;
         menu.set_position(0.0, -50.0);
 
-        let mut cursor = Texture::new("./res/gui/cursor.png", display);
+        let mut cursor = Texture::new("./res/gui/cursor.png", display)?;
         cursor.scale(1.3);
         cursor.set_position(0.0, 10.0);
 
-        let mut title = Texture::new("./res/gui/game_paused.png", display);
+        let mut title = Texture::new("./res/gui/game_paused.png", display)?;
         title.set_position(0.0, 80.0);
 
-        Self {
-            menu: menu,
-            cursor: cursor,
-            panel: Texture::new("./res/gui/panel.png", display),
+        // under the menu, wrapped to stay inside the panel
+        let mut seed = Text::new(display, "")?;
+        seed.set_depth(LABEL_DEPTH);
+        seed.set_position(0.0, -152.0);
+        seed.set_align(Align::Center);
+        seed.set_color(TEXT_COLOR);
+        seed.set_scale(0.75);
+        seed.set_wrap(Some(420.0));
+
+        Ok(Self {
+            menu,
+            cursor,
+            panel: Texture::new("./res/gui/panel.png", display)?,
             menu_choice: 0,
-            title: title,
+            title,
+            seed,
             elapsed_time: 0.0,
-        }
+        })
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed.set_text(&format!(
+            "Seed {}. Start with --seed {} to play this layout again.",
+            seed, seed
+        ));
     }
 
     pub fn update(&mut self, input: &mut InputManager, dt: f32) {
@@ -204,11 +232,12 @@ impl Pause {
         }
     }
 
-    pub fn draw(&mut self, target: &mut Frame, program: &Program) {
-        self.panel.draw(target, program);
-        self.menu.draw(target, program);
-        self.title.draw(target, program);
-        self.cursor.draw(target, program);
+    pub fn draw(&mut self, batch: &mut SpriteBatch) {
+        self.panel.draw(batch);
+        self.menu.draw(batch);
+        self.title.draw(batch);
+        self.cursor.draw(batch);
+        self.seed.draw(batch);
     }
 }
 
@@ -219,27 +248,80 @@ pub struct GameOver {
     pub menu_choice: i8,
     elapsed_time: f32,
     panel: Texture,
+    seed_label: Text,
+    seed: NumberDisplay,
+    distance_label: Text,
+    distance: NumberDisplay,
+    best: Text,
 }
 
 impl GameOver {
-    pub fn new(display: &Display) -> Self {
-        let mut texture = Texture::new("./res/gui/game_over.png", display);
+    pub fn new(display: &Display) -> Result<Self, AssetError> {
+        let mut texture = Texture::new("./res/gui/game_over.png", display)?;
         texture.set_y(80.0);
 
-        let mut menu = Texture::new("./res/gui/game_over_menu.png", display);
+        let mut menu = Texture::new("./res/gui/game_over_menu.png", display)?;
         menu.set_position(0.0, -50.0);
 
-        let mut cursor = Texture::new("./res/gui/cursor.png", display);
+        let mut cursor = Texture::new("./res/gui/cursor.png", display)?;
         cursor.set_position(0.0, -10.0);
 
-        Self {
-            texture: texture,
-            menu: menu,
-            cursor: cursor,
+        // along the bottom of the panel, one in each corner
+        let mut seed_label = Text::new(display, "SEED")?;
+        seed_label.set_depth(LABEL_DEPTH);
+        seed_label.set_position(-225.0, -118.0);
+        seed_label.set_color(TEXT_COLOR);
+
+        let mut seed = NumberDisplay::new(display)?;
+        seed.set_depth(LABEL_DEPTH);
+        seed.set_leading_zeros(SEED_DIGITS);
+        seed.set_position(-225.0, -150.0);
+
+        let mut distance_label = Text::new(display, "DISTANCE")?;
+        distance_label.set_depth(LABEL_DEPTH);
+        distance_label.set_position(225.0, -118.0);
+        distance_label.set_align(Align::Right);
+        distance_label.set_color(TEXT_COLOR);
+
+        let mut distance = NumberDisplay::new(display)?;
+        distance.set_depth(LABEL_DEPTH);
+        distance.set_separators(true);
+        distance.set_align(Align::Right);
+        distance.set_position(225.0, -150.0);
+
+        let mut best = Text::new(display, "")?;
+        best.set_depth(LABEL_DEPTH);
+        best.set_position(0.0, 140.0);
+        best.set_align(Align::Center);
+        best.set_color(TEXT_COLOR);
+
+        Ok(Self {
+            texture,
+            menu,
+            cursor,
             menu_choice: 0,
             elapsed_time: 0.0,
-            panel: Texture::new("./res/gui/panel.png", display),
-        }
+            panel: Texture::new("./res/gui/panel.png", display)?,
+            seed_label,
+            seed,
+            distance_label,
+            distance,
+            best,
+        })
+    }
+
+    // `best` is the distance to beat, if there is one; replays have none.
+    pub fn set_run(&mut self, seed: u64, distance: u32, best: Option<u32>, is_best: bool) {
+        self.seed.set(seed);
+        self.distance.set(0);
+        self.distance.count_to(distance as u64);
+        let best = match best {
+            _ if is_best => "NEW BEST!".to_string(),
+            Some(best) => format!("BEST {}", best),
+            None => String::new(),
+        };
+        self.best.set_text(&best);
+        self.best.set_scale(if is_best { 1.5 } else { 1.0 });
     }
 
     pub fn update(&mut self, input: &mut InputManager, dt: f32) {
@@ -248,6 +330,7 @@ impl GameOver {
         }
 
         self.elapsed_time += dt;
+        self.distance.update(dt);
 
         let t = self.elapsed_time * 3.5;
 
@@ -264,10 +347,15 @@ impl GameOver {
         }
     }
 
-    pub fn draw(&mut self, target: &mut Frame, program: &Program) {
-        self.panel.draw(target, program);
-        self.texture.draw(target, program);
-        self.menu.draw(target, program);
-        self.cursor.draw(target, program);
+    pub fn draw(&mut self, batch: &mut SpriteBatch) {
+        self.panel.draw(batch);
+        self.texture.draw(batch);
+        self.menu.draw(batch);
+        self.cursor.draw(batch);
+        self.seed_label.draw(batch);
+        self.seed.draw(batch);
+        self.distance_label.draw(batch);
+        self.distance.draw(batch);
+        self.best.draw(batch);
     }
 }
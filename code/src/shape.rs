@@ -1,4 +1,6 @@
-use glium::{implement_vertex, uniform, Display, Surface};
+use glium::implement_vertex;
+
+use crate::batch::SpriteBatch;
 
 pub const SCREEN_HEIGHT: f32 = 576.0; // 12
 pub const SCREEN_WIDTH: f32 = 768.0; // 16
@@ -9,12 +11,14 @@ pub const RIGHT: f32 = SCREEN_WIDTH / 2.0;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Vertex {
-    position: [f32; 2],
-    color: [f32; 4],
-    tex_coords: [f32; 2],
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+    pub tex_coords: [f32; 2],
+    // how far towards white, and hue rotation in turns
+    pub effect: [f32; 2],
 }
 
-implement_vertex!(Vertex, position, color, tex_coords);
+implement_vertex!(Vertex, position, color, tex_coords, effect);
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Direction {
@@ -27,65 +31,85 @@ pub struct Rectangle {
     pub height: f32,
 
     pub vertex_array: Vec<Vertex>,
-    pub vertex_buffer: glium::VertexBuffer<Vertex>,
-    pub index_buffer: glium::index::NoIndices,
     pub matrix: [[f32; 4]; 4],
+    pub depth: i32,
 }
 
 impl Rectangle {
-    pub fn new(display: &glium::Display, w: f32, h: f32) -> Self {
-        let x = ((w as f32) * 2. / SCREEN_WIDTH) / 2.;
-        let y = ((h as f32) * 2. / SCREEN_HEIGHT) / 2.;
+    pub fn new(w: f32, h: f32) -> Self {
+        let x = (w * 2. / SCREEN_WIDTH) / 2.;
+        let y = (h * 2. / SCREEN_HEIGHT) / 2.;
 
         let vertex1 = Vertex {
             //btm right
             position: [x, -y],
             color: [0.0, 0.0, 1.0, 1.0],
             tex_coords: [1.0, 0.0],
+            effect: [0.0, 0.0],
         };
         let vertex2 = Vertex {
             // top right
             position: [x, y],
             color: [0.0, 1.0, 0.0, 1.0],
             tex_coords: [1.0, 1.0],
+            effect: [0.0, 0.0],
         };
         let vertex3 = Vertex {
             //btm left
             position: [-x, -y],
             color: [1.0, 0.0, 1.0, 1.0],
             tex_coords: [0.0, 0.0],
+            effect: [0.0, 0.0],
         };
         let vertex4 = Vertex {
             //top left
             position: [-x, y],
             color: [0.0, 0.0, 1.0, 1.0],
             tex_coords: [0.0, 1.0],
+            effect: [0.0, 0.0],
         };
 
         let shape = vec![vertex1, vertex2, vertex3, vertex4];
         Self {
-            width: w as f32,
-            height: h as f32,
-            vertex_array: shape.clone(),
-            vertex_buffer: glium::VertexBuffer::new(display, &shape).unwrap(),
-            index_buffer: glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip),
+            width: w,
+            height: h,
+            vertex_array: shape,
             matrix: [
                 [1.0, 0.0, 0.0, 0.0],
                 [0.0, 1.0, 0.0, 0.0],
                 [0.0, 0.0, 1.0, 0.0],
                 [0.0, 0.0, 0.0, 1.0f32],
             ],
+            depth: 0,
+        }
+    }
+
+    // Resizes the quad in place, keeping any scale applied to it.
+    pub fn set_size(&mut self, w: f32, h: f32) {
+        let x = (w * 2. / SCREEN_WIDTH) / 2.;
+        let y = (h * 2. / SCREEN_HEIGHT) / 2.;
+
+        let positions = [[x, -y], [x, y], [-x, -y], [-x, y]];
+        for (vertex, position) in self.vertex_array.iter_mut().zip(positions) {
+            vertex.position = position;
         }
+
+        self.width = w * self.matrix[0][0];
+        self.height = h * self.matrix[1][1];
+    }
+
+    // Higher depths draw over lower ones in the same layer.
+    pub fn set_depth(&mut self, depth: i32) {
+        self.depth = depth;
     }
 
-    pub fn set_color(&mut self, display: &Display, color: [f32; 4]) {
+    pub fn set_color(&mut self, color: [f32; 4]) {
         for i in 0..4 {
             self.vertex_array[i].color = color;
         }
-        self.vertex_buffer = glium::VertexBuffer::new(display, &self.vertex_array).unwrap();
     }
 
-    pub fn set_gradient(&mut self, display: &Display, c1: [f32; 4], c2: [f32; 4], dir: Direction) {
+    pub fn set_gradient(&mut self, c1: [f32; 4], c2: [f32; 4], dir: Direction) {
         match dir {
             Direction::Horizontal => {
                 self.vertex_array[0].color = c1;
@@ -100,33 +124,31 @@ impl Rectangle {
                 self.vertex_array[3].color = c2;
             }
         }
-        self.vertex_buffer = glium::VertexBuffer::new(display, &self.vertex_array).unwrap();
     }
 
-    pub fn flip_tex_coords(&mut self, display: &Display, dir: Direction){
+    pub fn flip_tex_coords(&mut self, dir: Direction) {
         match dir {
             Direction::Horizontal => {
                 // 0-2, 1-3
-                let temp=self.vertex_array[0].tex_coords;
-                self.vertex_array[0].tex_coords=self.vertex_array[2].tex_coords;
-                self.vertex_array[2].tex_coords=temp;
+                let temp = self.vertex_array[0].tex_coords;
+                self.vertex_array[0].tex_coords = self.vertex_array[2].tex_coords;
+                self.vertex_array[2].tex_coords = temp;
 
-                let temp=self.vertex_array[1].tex_coords;
-                self.vertex_array[1].tex_coords=self.vertex_array[3].tex_coords;
-                self.vertex_array[3].tex_coords=temp;
+                let temp = self.vertex_array[1].tex_coords;
+                self.vertex_array[1].tex_coords = self.vertex_array[3].tex_coords;
+                self.vertex_array[3].tex_coords = temp;
             }
             Direction::Vertical => {
                 // 0-1 2-3
-                let temp=self.vertex_array[0].tex_coords;
-                self.vertex_array[0].tex_coords=self.vertex_array[1].tex_coords;
-                self.vertex_array[1].tex_coords=temp;
+                let temp = self.vertex_array[0].tex_coords;
+                self.vertex_array[0].tex_coords = self.vertex_array[1].tex_coords;
+                self.vertex_array[1].tex_coords = temp;
 
-                let temp=self.vertex_array[2].tex_coords;
-                self.vertex_array[2].tex_coords=self.vertex_array[3].tex_coords;
-                self.vertex_array[3].tex_coords=temp;
+                let temp = self.vertex_array[2].tex_coords;
+                self.vertex_array[2].tex_coords = self.vertex_array[3].tex_coords;
+                self.vertex_array[3].tex_coords = temp;
             }
         }
-        self.vertex_buffer = glium::VertexBuffer::new(display, &self.vertex_array).unwrap();
     }
 
     pub fn scale(&mut self, factor: f32) {
@@ -151,33 +173,33 @@ impl Rectangle {
         self.set_y(y);
     }
 
-    pub fn set_x(&mut self, x: f32){
+    pub fn set_x(&mut self, x: f32) {
         let x0 = x * 2. / SCREEN_WIDTH;
         self.matrix[3][0] = x0;
     }
 
-    pub fn set_y(&mut self, y: f32){
+    pub fn set_y(&mut self, y: f32) {
         let y0 = y * 2. / SCREEN_HEIGHT;
         self.matrix[3][1] = y0;
     }
 
-    pub fn get_position(&mut self) -> (f32, f32) {
-        (self.matrix[3][0] * RIGHT, self.matrix[3][1] * TOP)
+    // Corners in screen space, in the order the batch expects: bottom right,
+    // top right, bottom left, top left.
+    pub fn vertices(&self) -> [Vertex; 4] {
+        let m = self.matrix;
+        std::array::from_fn(|i| {
+            let v = self.vertex_array[i];
+            Vertex {
+                position: [
+                    m[0][0] * v.position[0] + m[3][0],
+                    m[1][1] * v.position[1] + m[3][1],
+                ],
+                ..v
+            }
+        })
     }
 
-    pub fn draw(&self, target: &mut glium::Frame, program: &glium::Program) {
-        let uniforms = uniform! {
-            isTex: false,
-            matrix: self.matrix,
-        };
-        target
-            .draw(
-                &self.vertex_buffer,
-                &self.index_buffer,
-                program,
-                &uniforms,
-                &Default::default(),
-            )
-            .unwrap();
+    pub fn draw(&self, batch: &mut SpriteBatch) {
+        batch.push(None, self.depth, self.vertices());
     }
 }
@@ -0,0 +1,53 @@
+// Units are pixels, pixels per second and pixels per second squared, so
+// every value here is independent of the step rate.
+
+pub const GRAVITY: f32 = 360.0;
+pub const TERMINAL_VELOCITY: f32 = 600.0;
+pub const FLAP_VELOCITY: f32 = 380.0;
+pub const RUN_SPEED: f32 = 200.0;
+pub const FLYING_ENEMY_SPEED: f32 = 120.0;
+pub const LAND_ENEMY_SPEED: f32 = 150.0;
+// How fast the level goes by once Ubi runs past the camera's dead zone.
+pub const SCROLL_SPEED: f32 = 80.0;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Body {
+    pub x: f32,
+    pub y: f32,
+    pub velocity: [f32; 2],
+    pub acceleration: [f32; 2],
+    pub terminal_velocity: f32,
+}
+
+impl Body {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self {
+            x,
+            y,
+            velocity: [0.0, 0.0],
+            acceleration: [0.0, -GRAVITY],
+            terminal_velocity: TERMINAL_VELOCITY,
+        }
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    pub fn stop(&mut self) {
+        self.velocity = [0.0, 0.0];
+    }
+
+    // Position moves before velocity picks up acceleration: a body whose
+    // vertical velocity was zeroed by a landing stays put for the next step
+    // instead of sinking into the platform.
+    pub fn integrate(&mut self, dt: f32) {
+        self.x += self.velocity[0] * dt;
+        self.y += self.velocity[1] * dt;
+
+        self.velocity[0] += self.acceleration[0] * dt;
+        self.velocity[1] += self.acceleration[1] * dt;
+        self.velocity[1] = self.velocity[1].max(-self.terminal_velocity);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::shape::LEFT;
+
+// How far right of the screen centre the player can get before the camera
+// starts to follow.
+const DEAD_ZONE: f32 = 64.0;
+// Rate at which the camera closes the gap to where it wants to be, per
+// second. Higher is snappier.
+const SMOOTHING: f32 = 6.0;
+
+// The level is only generated ahead of the player, so the camera follows to
+// the right and never scrolls back. `x` is the world position shown at the
+// centre of the screen.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Camera {
+    pub x: f32,
+    target: f32,
+}
+
+impl Camera {
+    pub fn follow(&mut self, focus: f32, dt: f32) {
+        self.target = self.target.max(focus - DEAD_ZONE);
+        self.x += (self.target - self.x) * (1.0 - (-SMOOTHING * dt).exp());
+    }
+
+    // The right edge of the dead zone, in world space. It's measured from
+    // where the camera is heading rather than where it is, so the pace
+    // doesn't change while the camera catches up.
+    pub fn edge(&self) -> f32 {
+        self.target + DEAD_ZONE
+    }
+
+    pub fn left(&self) -> f32 {
+        self.x + LEFT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stays_put_inside_the_dead_zone() {
+        let mut camera = Camera::default();
+        for _ in 0..100 {
+            camera.follow(DEAD_ZONE - 1.0, 0.1);
+        }
+        assert_eq!(camera.x, 0.0);
+        assert_eq!(camera.edge(), DEAD_ZONE);
+    }
+
+    #[test]
+    fn follows_past_the_dead_zone_and_never_back() {
+        let mut camera = Camera::default();
+        for _ in 0..100 {
+            camera.follow(DEAD_ZONE + 200.0, 0.1);
+        }
+        assert!((camera.x - 200.0).abs() < 0.01);
+        assert_eq!(camera.edge(), DEAD_ZONE + 200.0);
+
+        camera.follow(-500.0, 0.1);
+        assert!((camera.x - 200.0).abs() < 0.01);
+    }
+}
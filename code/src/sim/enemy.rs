@@ -0,0 +1,95 @@
+use crate::{
+    collision::{Aabb, Collider, Hitbox},
+    shape::BOTTOM,
+};
+
+use super::{body::FLYING_ENEMY_SPEED, Body};
+
+pub const SPAWN_DELAY: f32 = 30.0;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Species {
+    Land,
+    Flying,
+}
+
+impl Species {
+    pub fn dimensions(&self) -> (f32, f32) {
+        match self {
+            Species::Land => (54.0, 60.0),
+            Species::Flying => (102.0, 36.0),
+        }
+    }
+
+    // The body without the transparent margins of the sprite; the land
+    // enemy's horns and the tips of the flying one's wings don't count.
+    pub fn hitbox(&self) -> Hitbox {
+        match self {
+            Species::Land => Hitbox::new(44.0, 50.0).with_offset(0.0, -5.0),
+            Species::Flying => Hitbox::new(80.0, 28.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnemyState {
+    pub body: Body,
+    pub width: f32,
+    pub height: f32,
+    pub hitbox: Hitbox,
+    pub species: Species,
+    pub is_dead: bool,
+}
+
+impl EnemyState {
+    pub fn new(species: Species) -> Self {
+        let (width, height) = species.dimensions();
+        Self {
+            body: Body::new(0.0, 0.0),
+            width,
+            height,
+            hitbox: species.hitbox(),
+            species,
+            is_dead: false,
+        }
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.body.set_position(x, y);
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.is_dead
+    }
+
+    pub fn set_dead(&mut self, b: bool) {
+        self.is_dead = b;
+        self.body.stop();
+    }
+
+    pub fn translate(&mut self, x: f32, y: f32) {
+        if !self.is_dead {
+            self.body.x += x;
+            self.body.y += y;
+        }
+    }
+
+    pub fn fly(&mut self, dt: f32) {
+        if !self.is_dead {
+            self.translate(-FLYING_ENEMY_SPEED * dt, 0.0);
+        }
+    }
+
+    pub fn apply_gravity(&mut self, dt: f32) {
+        self.body.integrate(dt);
+        if self.body.y < BOTTOM - self.height {
+            self.body.stop();
+        }
+    }
+}
+
+impl Collider for EnemyState {
+    fn aabb(&self) -> Aabb {
+        self.hitbox.at(self.body.x, self.body.y)
+    }
+}
@@ -0,0 +1,37 @@
+// Game rules with no dependency on an OpenGL context. Everything in here
+// advances from a time step and an `Input` snapshot only; the sprites in
+// the rest of the crate mirror this state when drawing.
+
+mod body;
+mod camera;
+mod enemy;
+mod platform;
+mod player;
+#[cfg(test)]
+pub mod testing;
+mod world;
+
+pub use body::Body;
+pub use camera::Camera;
+pub use enemy::{EnemyState, Species, SPAWN_DELAY};
+pub use platform::{PlatformState, Size, Type};
+pub use player::PlayerState;
+pub use world::{Event, Input, Scores, World};
+
+// Every step advances the world by exactly this much; replays are only
+// reproducible because of it.
+pub const FIXED_DT: f32 = 1.0 / 120.0;
+
+// Steps further apart than this are teleports (respawns, recycled
+// platforms) and snap rather than sliding across the screen.
+const SNAP_DISTANCE: f32 = 100.0;
+
+pub fn interpolate(prev: (f32, f32), current: (f32, f32), alpha: f32) -> (f32, f32) {
+    if (current.0 - prev.0).abs() > SNAP_DISTANCE || (current.1 - prev.1).abs() > SNAP_DISTANCE {
+        return current;
+    }
+    (
+        prev.0 + (current.0 - prev.0) * alpha,
+        prev.1 + (current.1 - prev.1) * alpha,
+    )
+}
@@ -0,0 +1,222 @@
+use crate::collision::{Aabb, Collider};
+
+use super::{body::LAND_ENEMY_SPEED, EnemyState, Event, PlayerState, Scores, Species};
+
+pub const PLATFORM_HEIGHT: f32 = 96.0;
+pub const FISH_WIDTH: f32 = 18.0;
+pub const FISH_HEIGHT: f32 = 30.0;
+const FISH_BOB_SPEED: f32 = 2.4;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Size {
+    Small,
+    Medium,
+    Large,
+    XLarge,
+}
+
+impl Size {
+    pub fn from_u32(value: u32) -> Size {
+        match value {
+            0 => Size::Small,
+            1 => Size::Medium,
+            2 => Size::Large,
+            _ => panic!("Unknown value: {}", value),
+        }
+    }
+
+    pub fn width(&self) -> f32 {
+        match self {
+            Size::Small => 96.0,
+            Size::Medium => 144.0,
+            Size::Large => 240.0,
+            Size::XLarge => 336.0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Type {
+    Enemy,
+    Fish,
+    Plain,
+}
+
+#[derive(Debug, Clone)]
+pub struct FishState {
+    pub x: f32,
+    pub y: f32,
+    pub taken: bool,
+}
+
+// Pickups are caught by their whole sprite, which is generous enough
+// already.
+impl Collider for FishState {
+    fn aabb(&self) -> Aabb {
+        Aabb::new(self.x, self.y, FISH_WIDTH, FISH_HEIGHT)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlatformState {
+    pub width: f32,
+    pub height: f32,
+    pub x: f32,
+    pub y: f32,
+    pub size: Size,
+    pub enemies: Vec<EnemyState>,
+    pub enemy_speed: f32,
+    pub fish: Vec<FishState>,
+    pub elapsed_time: f32,
+    pub platform_type: Type,
+}
+
+impl PlatformState {
+    pub fn new(size: Size) -> Self {
+        let width = size.width();
+
+        let mut enemies: Vec<EnemyState> = vec![];
+        let mut fish: Vec<FishState> = vec![];
+
+        if size != Size::Small {
+            let n = (width / 48.0) as i32;
+
+            let mut e = EnemyState::new(Species::Land);
+            e.set_position(0.0, 24.0);
+            enemies.push(e);
+
+            for i in -(n / 2)..=n / 2 {
+                fish.push(FishState {
+                    x: i as f32 * 48.0,
+                    y: 36.0,
+                    taken: false,
+                });
+            }
+        }
+
+        Self {
+            width,
+            height: PLATFORM_HEIGHT,
+            x: 0.0,
+            y: 0.0,
+            size,
+            enemies,
+            enemy_speed: LAND_ENEMY_SPEED,
+            fish,
+            elapsed_time: 0.0,
+            platform_type: Type::Plain,
+        }
+    }
+
+    pub fn set_type(&mut self, t: Type) {
+        self.platform_type = t;
+        match t {
+            Type::Fish => {
+                for fish in self.fish.iter_mut() {
+                    fish.taken = false;
+                }
+            }
+            Type::Enemy => {
+                for enemy in self.enemies.iter_mut() {
+                    enemy.set_dead(false);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+        if self.size == Size::Small {
+            return;
+        }
+        let n = (self.width / 48.0) as usize;
+        for i in n / 2..self.fish.len() {
+            self.fish[i].x = self.x + (i - n / 2) as f32 * 48.0;
+            self.fish[i].y = self.y + 84.0;
+        }
+        for i in (0..=n / 2).rev() {
+            self.fish[i].x = self.x + i as f32 * -48.0;
+            self.fish[i].y = self.y + 84.0;
+        }
+        for enemy in self.enemies.iter_mut() {
+            enemy.set_position(self.x, self.y + 78.0);
+        }
+    }
+
+    pub fn player_took_fish(
+        &mut self,
+        player: &PlayerState,
+        scores: &mut Scores,
+        events: &mut Vec<Event>,
+    ) {
+        for fish in self.fish.iter_mut() {
+            if !fish.taken && fish.intersects(player) {
+                scores.fish += 1;
+                fish.taken = true;
+                events.push(Event::Fish {
+                    x: fish.x,
+                    y: fish.y,
+                });
+            }
+        }
+    }
+
+    pub fn player_vs_enemy(
+        &mut self,
+        player: &mut PlayerState,
+        scores: &mut Scores,
+        events: &mut Vec<Event>,
+    ) {
+        for enemy in self.enemies.iter_mut() {
+            player.check_interaction(enemy, scores, events);
+        }
+    }
+
+    pub fn player_is_on(&self, player: &mut PlayerState) -> bool {
+        let b = player.aabb().overlaps_x(&self.aabb());
+        player.set_on_platform(b);
+
+        b
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        match self.platform_type {
+            Type::Enemy => {
+                for i in 0..self.enemies.len() {
+                    let x = self.enemies[i].body.x;
+                    if x + 32.0 >= self.x + self.width / 2. || x - 32.0 <= self.x - self.width / 2.
+                    {
+                        self.enemy_speed *= -1.0;
+                    }
+                    self.enemies[i].translate(self.enemy_speed * dt, 0.0);
+                }
+            }
+            Type::Fish => {
+                for i in 0..self.fish.len() {
+                    if self.elapsed_time > 999. {
+                        self.elapsed_time = 1.0;
+                    }
+                    self.elapsed_time += dt;
+
+                    let t = self.elapsed_time * 1.5;
+                    let y = t.sin() * FISH_BOB_SPEED * dt;
+
+                    if i % 2 == 1 {
+                        self.fish[i].y += y;
+                    } else {
+                        self.fish[i].y -= y;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Collider for PlatformState {
+    fn aabb(&self) -> Aabb {
+        Aabb::new(self.x, self.y, self.width, self.height)
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{
+    collision::{Aabb, Collider, Hitbox, Side},
+    shape::BOTTOM,
+};
+
+use super::{
+    body::{FLAP_VELOCITY, RUN_SPEED},
+    Body, EnemyState, Event, Input, Scores,
+};
+
+pub const PLAYER_WIDTH: f32 = 62.0;
+pub const PLAYER_HEIGHT: f32 = 79.0;
+// Narrower than the sprite and without the propeller on top. The bottom
+// still lines up with Ubi's feet.
+const PLAYER_HITBOX: Hitbox = Hitbox::new(48.0, 64.0).with_offset(0.0, -7.5);
+
+const START_X: f32 = BOTTOM + 48.0;
+const START_Y: f32 = 120.0;
+
+#[derive(Debug, Clone)]
+pub struct PlayerState {
+    pub body: Body,
+    pub width: f32,
+    pub height: f32,
+    pub hitbox: Hitbox,
+    pub is_on_platform: bool,
+    pub distance: f32,
+    pub is_dead: bool,
+}
+
+impl PlayerState {
+    pub fn new() -> Self {
+        Self {
+            body: Body::new(START_X, START_Y),
+            width: PLAYER_WIDTH,
+            height: PLAYER_HEIGHT,
+            hitbox: PLAYER_HITBOX,
+            is_on_platform: false,
+            distance: 0.0,
+            is_dead: false,
+        }
+    }
+
+    pub fn set_on_platform(&mut self, b: bool) {
+        self.is_on_platform = b;
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.is_dead
+    }
+
+    pub fn set_dead(&mut self, b: bool) {
+        self.is_dead = b;
+    }
+
+    pub fn apply_gravity(&mut self, dt: f32) {
+        self.body.integrate(dt);
+        if self.body.y < BOTTOM - self.height {
+            self.body.stop();
+        }
+    }
+
+    pub fn check_interaction(
+        &mut self,
+        enemy: &mut EnemyState,
+        scores: &mut Scores,
+        events: &mut Vec<Event>,
+    ) {
+        if enemy.is_dead() {
+            return;
+        }
+        // coming down on an enemy's head stomps it, touching it anywhere
+        // else is fatal
+        match self.contact(enemy) {
+            Some(Side::Top) if !self.is_dead => {
+                scores.enemies += 1;
+                enemy.set_dead(true);
+                events.push(Event::Stomp {
+                    x: enemy.body.x,
+                    y: enemy.body.y,
+                });
+            }
+            Some(_) => self.set_dead(true),
+            None => {}
+        }
+    }
+
+    // `run_speed` is how fast holding right moves Ubi along.
+    pub fn update(&mut self, input: &Input, run_speed: f32, dt: f32) {
+        if self.is_dead() {
+            self.body.velocity[0] = 0.0;
+            self.apply_gravity(dt);
+            return;
+        }
+
+        if self.body.y < BOTTOM - self.height / 2.0 {
+            self.set_dead(true);
+            return;
+        }
+
+        self.body.velocity[0] = 0.0;
+        if input.right {
+            self.body.velocity[0] += run_speed;
+            self.distance += dt;
+        }
+        if input.left && self.is_on_platform {
+            self.body.velocity[0] -= RUN_SPEED;
+            self.distance -= dt;
+        }
+
+        self.apply_gravity(dt);
+
+        if input.flap {
+            self.body.velocity[1] = FLAP_VELOCITY;
+        }
+    }
+}
+
+impl Collider for PlayerState {
+    fn aabb(&self) -> Aabb {
+        self.hitbox.at(self.body.x, self.body.y)
+    }
+}
@@ -0,0 +1,12 @@
+// Fixtures shared by the tests of the simulation and of anything driving it.
+
+use super::Input;
+
+// Flaps every second while running right, with a pause now and then.
+pub fn scripted(tick: u32) -> Input {
+    Input {
+        flap: tick % 120 < 10,
+        left: tick % 500 > 450,
+        right: tick % 500 < 400,
+    }
+}
@@ -0,0 +1,496 @@
+use crate::{
+    collision::Collider,
+    rng::{roll, GameRng, ENEMY_STREAM, LEVEL_STREAM},
+    shape::{BOTTOM, LEFT, RIGHT, SCREEN_WIDTH, TOP},
+};
+
+use super::{
+    body::{RUN_SPEED, SCROLL_SPEED},
+    Camera, EnemyState, PlatformState, PlayerState, Size, Species, Type, SPAWN_DELAY,
+};
+
+// Edge to edge spacing of platforms. A platform is laid out again once it's
+// off the left of the screen; the other three are at least 480 wide between
+// them, so with four gaps of MIN_GAP the new one always starts off the right.
+const MIN_GAP: f32 = 80.0;
+const MAX_GAP: f32 = 240.0;
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Input {
+    pub flap: bool,
+    pub left: bool,
+    pub right: bool,
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Scores {
+    pub fish: u32,
+    pub enemies: u32,
+    pub distance: u32,
+}
+
+// Things that happened during a step, for effects to react to. The rules
+// never read them back.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Event {
+    Flap { x: f32, y: f32 },
+    Fish { x: f32, y: f32 },
+    Stomp { x: f32, y: f32 },
+    Death { x: f32, y: f32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct World {
+    pub player: PlayerState,
+    pub platforms: Vec<PlatformState>,
+    pub enemies: Vec<EnemyState>,
+    pub scores: Scores,
+    pub camera: Camera,
+    pub tick: u32,
+    pub events: Vec<Event>,
+    flapping: bool,
+    spawn_time: f32,
+    game_over_delay: f32,
+    // Enemies that fly back in once they're gone draw from a stream of their
+    // own, so how many the player knocks out never moves the level or the
+    // next enemy to spawn.
+    respawns: GameRng,
+    seed: u64,
+    // How many platforms have been laid out, and which was the latest.
+    placed: i64,
+    last: usize,
+}
+
+impl World {
+    pub fn new(seed: u64) -> Self {
+        let mut platforms: Vec<PlatformState> = vec![PlatformState::new(Size::XLarge)];
+
+        for i in 0..3 {
+            platforms.push(PlatformState::new(Size::from_u32(i % 3)));
+        }
+
+        let mut world = World {
+            player: PlayerState::new(),
+            platforms,
+            enemies: vec![],
+            scores: Scores::default(),
+            camera: Camera::default(),
+            tick: 0,
+            events: vec![],
+            flapping: false,
+            spawn_time: 0.0,
+            game_over_delay: 0.0,
+            respawns: GameRng::new(roll(seed, ENEMY_STREAM + 1, 0)),
+            seed,
+            placed: 0,
+            last: 1,
+        };
+        world.place_platforms();
+        world
+    }
+
+    fn place_platforms(&mut self) {
+        self.platforms[3].set_position(510.0, -100.0);
+        self.platforms[2].set_position(800.0, -150.0);
+        self.platforms[1].set_position(1060.0, 50.0);
+        self.platforms[0].set_position(LEFT + 100.0, -50.0);
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Starting over from a fresh world rather than resetting fields keeps
+    // every run on a seed identical, which replays depend on.
+    pub fn restart(&mut self, seed: u64) {
+        *self = World::new(seed);
+    }
+
+    pub fn game_over(&mut self, dt: f32) -> bool {
+        if self.player.is_dead() {
+            self.game_over_delay += dt;
+            if self.game_over_delay > 3.0 {
+                return true;
+            }
+        }
+        false
+    }
+
+    // Every platform goes a gap after the one laid out before it, with rolls
+    // of its own on the seed, so the layout never depends on how the run
+    // went. A ghost replays on a world of its own and has to land on the
+    // same platforms the live run draws.
+    fn regenerate_platform(&mut self, index: usize) {
+        let mut rand = GameRng::new(roll(self.seed, LEVEL_STREAM, self.placed));
+        let p = rand.gen_range(0..10);
+        if p < 5 {
+            self.platforms[index].set_type(Type::Fish);
+        } else if p < 8 {
+            self.platforms[index].set_type(Type::Enemy);
+        } else {
+            self.platforms[index].set_type(Type::Plain);
+        }
+        let previous = self.platforms[self.last].aabb().right();
+        let x = previous + rand.gen_range(MIN_GAP..MAX_GAP) + self.platforms[index].width / 2.0;
+        let y = rand.gen_range(BOTTOM + 100.0..TOP - 200.0);
+        self.platforms[index].set_position(x, y);
+        self.placed += 1;
+        self.last = index;
+    }
+
+    pub fn update(&mut self, input: &Input, dt: f32) {
+        self.tick += 1;
+        self.events.clear();
+        let alive = !self.player.is_dead();
+        // Ubi runs freely inside the dead zone, but past it the level only
+        // goes by at SCROLL_SPEED so the camera doesn't race ahead
+        let run_speed = if self.player.body.x >= self.camera.edge() {
+            SCROLL_SPEED
+        } else {
+            RUN_SPEED
+        };
+        let feet = self.player.aabb().bottom();
+        self.player.update(input, run_speed, dt);
+        // holding the key keeps Ubi up, but only pressing it is a flap
+        if input.flap && !self.flapping && !self.player.is_dead() {
+            let body = &self.player.body;
+            self.events.push(Event::Flap {
+                x: body.x,
+                y: body.y,
+            });
+        }
+        self.flapping = input.flap;
+
+        // the player can't walk back off the screen
+        let left_edge = self.camera.left() + self.player.width / 2.0;
+        if self.player.body.x < left_edge {
+            self.player.body.x = left_edge;
+        }
+
+        for i in 0..self.platforms.len() {
+            self.platforms[i].update(dt);
+
+            match self.platforms[i].platform_type {
+                Type::Fish => {
+                    self.platforms[i].player_took_fish(
+                        &self.player,
+                        &mut self.scores,
+                        &mut self.events,
+                    );
+                }
+                Type::Enemy => {
+                    self.platforms[i].player_vs_enemy(
+                        &mut self.player,
+                        &mut self.scores,
+                        &mut self.events,
+                    );
+                }
+                _ => {}
+            }
+
+            if self.platforms[i].aabb().right() < self.camera.left() {
+                self.regenerate_platform(i);
+            }
+
+            land(&mut self.player, &self.platforms[i], feet);
+
+            if self.platforms[i].player_is_on(&mut self.player) {
+                break;
+            }
+        }
+
+        for i in 0..self.enemies.len() {
+            if !self.enemies[i].is_dead() {
+                self.enemies[i].fly(dt);
+            } else {
+                self.enemies[i].apply_gravity(dt);
+            }
+
+            if self.enemies[i].body.x <= self.camera.left() - self.enemies[i].width
+                || self.enemies[i].body.y <= BOTTOM - self.enemies[i].height
+            {
+                let x = self.camera.x + self.respawns.gen_range(RIGHT..SCREEN_WIDTH);
+                let y = self.respawns.gen_range(BOTTOM + 40.0..TOP - 40.0);
+                self.enemies[i].set_position(x, y);
+                self.enemies[i].set_dead(false);
+            }
+
+            self.player
+                .check_interaction(&mut self.enemies[i], &mut self.scores, &mut self.events);
+        }
+
+        if alive && self.player.is_dead() {
+            let body = &self.player.body;
+            self.events.push(Event::Death {
+                x: body.x,
+                y: body.y,
+            });
+        }
+
+        self.camera.follow(self.player.body.x, dt);
+
+        if self.player.distance > 0.5 {
+            self.scores.distance += 1;
+            self.player.distance = 0.0;
+        }
+
+        self.spawn_time += dt;
+
+        if self.spawn_time >= SPAWN_DELAY {
+            let mut enemy = EnemyState::new(Species::Flying);
+            let mut rand = GameRng::new(roll(self.seed, ENEMY_STREAM, self.enemies.len() as i64));
+            let x = self.camera.x + rand.gen_range(RIGHT..SCREEN_WIDTH);
+            let y = rand.gen_range(BOTTOM..TOP);
+            enemy.set_position(x, y);
+            self.enemies.push(enemy);
+            self.spawn_time = 0.0;
+        }
+    }
+}
+
+// Sets Ubi down on top of a platform it fell into. Platforms can be jumped
+// up through, so only feet that were above the top before this step land.
+fn land(player: &mut PlayerState, platform: &PlatformState, feet: f32) {
+    if player.body.velocity[1] > 0.0 || feet < platform.aabb().top() {
+        return;
+    }
+    // a push straight up means Ubi came down on the top
+    if let Some([dx, dy]) = player.penetration(platform) {
+        if dx == 0.0 && dy > 0.0 {
+            player.body.y += dy;
+            player.body.velocity[1] = 0.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{testing::scripted, FIXED_DT};
+
+    const RIGHT_ONLY: Input = Input {
+        flap: false,
+        left: false,
+        right: true,
+    };
+
+    fn step(world: &mut World, input: Input, ticks: u32) {
+        for _ in 0..ticks {
+            world.update(&input, FIXED_DT);
+        }
+    }
+
+    #[test]
+    fn same_seed_and_inputs_give_the_same_world() {
+        let mut a = World::new(42);
+        let mut b = World::new(42);
+        for tick in 0..3000 {
+            a.update(&scripted(tick), FIXED_DT);
+            b.update(&scripted(tick), FIXED_DT);
+        }
+        assert_eq!(format!("{:?}", a), format!("{:?}", b));
+    }
+
+    // Every platform laid out during a run, in order.
+    fn layout(inputs: impl Fn(u32) -> Input) -> Vec<(f32, f32, Type)> {
+        let mut world = World::new(42);
+        let mut layout = vec![];
+        for tick in 0..6000 {
+            let placed = world.placed;
+            world.update(&inputs(tick), FIXED_DT);
+            if world.placed != placed {
+                let platform = &world.platforms[world.last];
+                // laid out off screen, never popping in on view
+                assert!(platform.aabb().left() > world.camera.x + RIGHT);
+                layout.push((platform.x, platform.y, platform.platform_type));
+            }
+        }
+        layout
+    }
+
+    // How fast the player gets along and what they run into changes when
+    // platforms are laid out, never where.
+    #[test]
+    fn layout_depends_on_the_seed_alone() {
+        let eager = layout(scripted);
+        let idle_start = layout(|tick| scripted(tick.saturating_sub(600)));
+        let held = layout(|_| Input {
+            flap: true,
+            ..RIGHT_ONLY
+        });
+        for other in [&idle_start, &held] {
+            let n = eager.len().min(other.len());
+            assert!(n >= 5, "only {} platforms laid out", n);
+            assert_eq!(eager[..n], other[..n]);
+        }
+    }
+
+    // Where each new enemy comes in, relative to the screen.
+    fn spawns(inputs: impl Fn(u32) -> Input) -> Vec<(f32, f32)> {
+        let mut world = World::new(42);
+        let mut spawns = vec![];
+        for tick in 0..(SPAWN_DELAY * 6.0 / FIXED_DT) as u32 {
+            let count = world.enemies.len();
+            world.update(&inputs(tick), FIXED_DT);
+            if world.enemies.len() != count {
+                let enemy = &world.enemies[count];
+                spawns.push((enemy.body.x - world.camera.x, enemy.body.y));
+            }
+        }
+        spawns
+    }
+
+    #[test]
+    fn enemies_spawn_the_same_whatever_the_inputs() {
+        let eager = spawns(scripted);
+        assert!(eager.len() >= 5, "only {} enemies spawned", eager.len());
+        let idle_start = spawns(|tick| scripted(tick.saturating_sub(600)));
+        let idle = spawns(|_| Input::default());
+        for other in [idle_start, idle] {
+            assert_eq!(eager.len(), other.len());
+            // measuring from the camera rounds a little differently
+            for (a, b) in eager.iter().zip(other) {
+                assert!((a.0 - b.0).abs() < 0.01 && a.1 == b.1, "{:?} {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn restart_matches_a_new_world() {
+        let mut world = World::new(7);
+        for tick in 0..1000 {
+            world.update(&scripted(tick), FIXED_DT);
+        }
+        world.restart(7);
+        assert_eq!(format!("{:?}", world), format!("{:?}", World::new(7)));
+    }
+
+    #[test]
+    fn player_lands_and_stays_on_the_first_platform() {
+        let mut world = World::new(1);
+        step(&mut world, Input::default(), 600);
+
+        let player = &world.player;
+        let platform = &world.platforms[0];
+        assert!(!player.is_dead());
+        assert!(player.is_on_platform);
+        let feet = player.aabb().bottom();
+        let ground = platform.aabb().top();
+        assert!(
+            (feet - ground).abs() < 1.0,
+            "feet at {}, ground at {}",
+            feet,
+            ground
+        );
+
+        let y = player.body.y;
+        for _ in 0..600 {
+            step(&mut world, Input::default(), 1);
+            assert_eq!(world.player.body.y, y);
+            assert!(world.player.body.velocity[1] <= 0.0);
+        }
+    }
+
+    // Runs off the right edge of the first platform into the gap before the
+    // next one, then lets go.
+    #[test]
+    fn falling_off_ends_the_game_after_three_seconds() {
+        let mut world = World::new(1);
+        let mut ticks = 0;
+        while !world.player.is_dead() {
+            assert!(ticks < 1200, "the player never fell");
+            world.update(&RIGHT_ONLY, FIXED_DT);
+            assert!(!world.game_over(FIXED_DT));
+            ticks += 1;
+        }
+        assert!(world
+            .events
+            .iter()
+            .any(|e| matches!(e, Event::Death { .. })));
+
+        let mut waited = 1;
+        loop {
+            world.update(&Input::default(), FIXED_DT);
+            waited += 1;
+            if world.game_over(FIXED_DT) {
+                break;
+            }
+            assert!(waited < 400, "the game never ended");
+        }
+        assert!((waited as f32 * FIXED_DT - 3.0).abs() <= 2.0 * FIXED_DT);
+    }
+
+    #[test]
+    fn running_past_the_dead_zone_slows_to_the_scroll_speed() {
+        let mut world = World::new(1);
+        step(&mut world, Input::default(), 1);
+        let inside = world.player.body.x;
+        step(&mut world, RIGHT_ONLY, 60);
+        let moved = world.player.body.x - inside;
+        assert!((moved - RUN_SPEED * 0.5).abs() < 1.0, "moved {}", moved);
+
+        // well above the platforms, so nothing gets in the way
+        let edge = world.camera.edge();
+        world.player.body.set_position(edge + 10.0, TOP);
+        step(&mut world, RIGHT_ONLY, 1);
+        let past = world.player.body.x;
+        step(&mut world, RIGHT_ONLY, 120);
+        let moved = world.player.body.x - past;
+        assert!((moved - SCROLL_SPEED).abs() < 1.0, "moved {}", moved);
+        assert!(world.player.body.x >= world.camera.edge());
+    }
+
+    // Drops the player from the air straight onto an enemy.
+    #[test]
+    fn landing_on_an_enemy_stomps_it() {
+        let mut world = World::new(1);
+        world.player.body.set_position(0.0, 200.0);
+        let mut enemy = EnemyState::new(Species::Flying);
+        enemy.set_position(0.0, 130.0);
+        world.enemies.push(enemy);
+
+        let mut stomped = false;
+        for _ in 0..60 {
+            world.update(&Input::default(), FIXED_DT);
+            stomped |= world
+                .events
+                .iter()
+                .any(|e| matches!(e, Event::Stomp { .. }));
+        }
+        assert!(stomped);
+        assert_eq!(world.scores.enemies, 1);
+        assert!(world.enemies[0].is_dead());
+        assert!(!world.player.is_dead());
+    }
+
+    // Platforms are one way: feet that end a jump inside one fall back out
+    // of the bottom instead of being lifted onto it.
+    #[test]
+    fn platforms_can_be_jumped_up_through() {
+        let mut world = World::new(1);
+        let (x, top) = (world.platforms[0].x, world.platforms[0].aabb().top());
+        let sunk = top - 10.0 - world.player.aabb().bottom();
+        world.player.body.x = x;
+        world.player.body.y += sunk;
+        let y = world.player.body.y;
+
+        step(&mut world, Input::default(), 30);
+        assert!(world.player.body.y < y);
+        assert!(world.player.aabb().bottom() < top - 10.0);
+    }
+
+    #[test]
+    fn running_into_an_enemy_is_fatal() {
+        let mut world = World::new(1);
+        step(&mut world, Input::default(), 240);
+        let (x, y) = (world.player.body.x, world.player.body.y);
+        let mut enemy = EnemyState::new(Species::Flying);
+        enemy.set_position(x + 100.0, y);
+        world.enemies.push(enemy);
+
+        step(&mut world, Input::default(), 120);
+        assert!(world.player.is_dead());
+        assert_eq!(world.scores.enemies, 0);
+        assert!(!world.enemies[0].is_dead());
+    }
+}
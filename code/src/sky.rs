@@ -0,0 +1,240 @@
+use crate::batch::{SpriteBatch, EFFECT_DEPTH};
+use crate::rng::{roll, WEATHER_STREAM};
+use crate::shape::{Direction, Rectangle, BOTTOM, LEFT, SCREEN_HEIGHT, SCREEN_WIDTH, TOP};
+
+// Distance covered in one full day. Each new day moves on to the next biome.
+const DAY_LENGTH: u32 = 400;
+const MAX_PARTICLES: usize = 400;
+
+type Color = [f32; 4];
+
+// Sky colours at each time of day as (top, bottom), in the order the day
+// runs: day, sunset, night, dawn.
+struct Palette {
+    keys: [(Color, Color); 4],
+}
+
+struct Weather {
+    color: Color,
+    size: (f32, f32),
+    // pixels per second
+    velocity: (f32, f32),
+    // how far particles swing from side to side
+    sway: f32,
+    // particles spawned per second
+    rate: f32,
+}
+
+struct Biome {
+    palette: Palette,
+    weather: Option<&'static Weather>,
+}
+
+const RAIN: Weather = Weather {
+    color: [0.55, 0.6, 0.9, 0.7],
+    size: (2.0, 14.0),
+    velocity: (-60.0, -600.0),
+    sway: 0.0,
+    rate: 200.0,
+};
+
+const SNOW: Weather = Weather {
+    color: [1.0, 1.0, 1.0, 0.9],
+    size: (4.0, 4.0),
+    velocity: (-10.0, -60.0),
+    sway: 20.0,
+    rate: 40.0,
+};
+
+const PETALS: Weather = Weather {
+    color: [1.0, 0.7, 0.8, 0.9],
+    size: (6.0, 4.0),
+    velocity: (-30.0, -45.0),
+    sway: 35.0,
+    rate: 12.0,
+};
+
+const NIGHT: (Color, Color) = ([0.05, 0.05, 0.25, 0.95], [0.25, 0.15, 0.45, 0.9]);
+
+const BIOMES: &[Biome] = &[
+    // meadow
+    Biome {
+        palette: Palette {
+            keys: [
+                ([0.3, 0.3, 1.0, 0.8], [1.0, 0.45, 1.0, 0.8]),
+                ([0.45, 0.2, 0.6, 0.85], [1.0, 0.55, 0.3, 0.85]),
+                NIGHT,
+                ([0.5, 0.45, 0.9, 0.8], [1.0, 0.75, 0.55, 0.8]),
+            ],
+        },
+        weather: None,
+    },
+    // orchard
+    Biome {
+        palette: Palette {
+            keys: [
+                ([0.45, 0.6, 1.0, 0.8], [1.0, 0.8, 0.9, 0.8]),
+                ([0.55, 0.25, 0.55, 0.85], [1.0, 0.6, 0.45, 0.85]),
+                NIGHT,
+                ([0.6, 0.5, 0.9, 0.8], [1.0, 0.8, 0.7, 0.8]),
+            ],
+        },
+        weather: Some(&PETALS),
+    },
+    // marsh
+    Biome {
+        palette: Palette {
+            keys: [
+                ([0.4, 0.45, 0.6, 0.85], [0.7, 0.75, 0.8, 0.85]),
+                ([0.35, 0.3, 0.45, 0.9], [0.7, 0.5, 0.45, 0.9]),
+                ([0.05, 0.05, 0.15, 0.95], [0.15, 0.15, 0.25, 0.9]),
+                ([0.45, 0.45, 0.6, 0.85], [0.8, 0.7, 0.65, 0.85]),
+            ],
+        },
+        weather: Some(&RAIN),
+    },
+    // tundra
+    Biome {
+        palette: Palette {
+            keys: [
+                ([0.55, 0.7, 1.0, 0.8], [0.9, 0.95, 1.0, 0.8]),
+                ([0.5, 0.4, 0.75, 0.85], [1.0, 0.7, 0.6, 0.85]),
+                ([0.0, 0.05, 0.2, 0.95], [0.1, 0.25, 0.4, 0.9]),
+                ([0.6, 0.6, 0.9, 0.8], [0.95, 0.8, 0.8, 0.8]),
+            ],
+        },
+        weather: Some(&SNOW),
+    },
+];
+
+fn lerp(a: Color, b: Color, t: f32) -> Color {
+    std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+// Maps a roll onto [0, 1).
+fn unit(roll: u64) -> f32 {
+    (roll >> 40) as f32 / (1u64 << 24) as f32
+}
+
+struct Particle {
+    weather: &'static Weather,
+    x: f32,
+    y: f32,
+    phase: f32,
+}
+
+pub struct Sky {
+    gradient: Rectangle,
+    particles: Vec<Particle>,
+    particle: Rectangle,
+    spawn_timer: f32,
+    spawned: i64,
+    time: f32,
+    seed: u64,
+    show_weather: bool,
+}
+
+impl Sky {
+    pub fn new(seed: u64, weather: bool) -> Self {
+        let mut particle = Rectangle::new(1.0, 1.0);
+        particle.set_depth(EFFECT_DEPTH);
+
+        let mut sky = Sky {
+            gradient: Rectangle::new(SCREEN_WIDTH, SCREEN_HEIGHT),
+            particles: Vec::with_capacity(MAX_PARTICLES),
+            particle,
+            spawn_timer: 0.0,
+            spawned: 0,
+            time: 0.0,
+            seed,
+            show_weather: weather,
+        };
+        sky.set_time_of_day(0);
+        sky
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.spawned = 0;
+        self.particles.clear();
+    }
+
+    fn set_time_of_day(&mut self, distance: u32) {
+        let day = (distance / DAY_LENGTH) as usize;
+        let biome = &BIOMES[day % BIOMES.len()];
+        let next = &BIOMES[(day + 1) % BIOMES.len()];
+
+        let t = (distance % DAY_LENGTH) as f32 / DAY_LENGTH as f32 * 4.0;
+        let key = t as usize;
+        let from = biome.palette.keys[key];
+        // dawn blends into the next biome's day
+        let to = if key == 3 {
+            next.palette.keys[0]
+        } else {
+            biome.palette.keys[key + 1]
+        };
+
+        let blend = t.fract();
+        self.gradient.set_gradient(
+            lerp(from.1, to.1, blend),
+            lerp(from.0, to.0, blend),
+            Direction::Vertical,
+        );
+    }
+
+    fn weather(distance: u32) -> Option<&'static Weather> {
+        BIOMES[(distance / DAY_LENGTH) as usize % BIOMES.len()].weather
+    }
+
+    pub fn update(&mut self, dt: f32, distance: u32) {
+        self.set_time_of_day(distance);
+        self.time += dt;
+
+        for particle in self.particles.iter_mut() {
+            particle.x += particle.weather.velocity.0 * dt;
+            particle.y += particle.weather.velocity.1 * dt;
+        }
+        self.particles
+            .retain(|particle| particle.y > BOTTOM - particle.weather.size.1);
+
+        let weather = Sky::weather(distance).filter(|_| self.show_weather);
+        let Some(weather) = weather else {
+            self.spawn_timer = 0.0;
+            return;
+        };
+
+        // particles start across a strip wider than the screen, so the
+        // sideways drift doesn't leave one edge bare
+        self.spawn_timer += weather.rate * dt;
+        while self.spawn_timer >= 1.0 {
+            self.spawn_timer -= 1.0;
+            if self.particles.len() >= MAX_PARTICLES {
+                continue;
+            }
+            let across = unit(roll(self.seed, WEATHER_STREAM, self.spawned));
+            let phase = unit(roll(self.seed, WEATHER_STREAM + 1, self.spawned));
+            self.spawned += 1;
+            self.particles.push(Particle {
+                weather,
+                x: LEFT + across * SCREEN_WIDTH * 1.5,
+                y: TOP + weather.size.1,
+                phase: phase * std::f32::consts::TAU,
+            });
+        }
+    }
+
+    pub fn draw(&self, batch: &mut SpriteBatch) {
+        self.gradient.draw(batch);
+    }
+
+    pub fn draw_weather(&mut self, batch: &mut SpriteBatch) {
+        for particle in self.particles.iter() {
+            let weather = particle.weather;
+            let sway = (self.time * 2.0 + particle.phase).sin() * weather.sway;
+            self.particle.set_size(weather.size.0, weather.size.1);
+            self.particle.set_color(weather.color);
+            self.particle.set_position(particle.x + sway, particle.y);
+            self.particle.draw(batch);
+        }
+    }
+}
@@ -0,0 +1,435 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use glium::Display;
+
+use crate::assets::{self, AssetError, Sprite};
+use crate::batch::SpriteBatch;
+use crate::shape::{Vertex, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+const FONT_IMAGE: &str = "./res/font/font.png";
+// Glyph placement and kerning in the AngelCode BMFont text format, so the
+// font can be regenerated with any tool that exports it.
+const FONT_METRICS: &str = "./res/font/font.fnt";
+
+// Shown in place of characters the font doesn't have.
+const FALLBACK: char = '?';
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+// Where a character sits on the font image and how it's placed on a line,
+// all in pixels.
+struct Glyph {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    x_offset: f32,
+    y_offset: f32,
+    advance: f32,
+}
+
+// Glyph layout parsed from the metrics file. It doesn't need the font
+// image, so wrapping and measuring work without a display.
+struct Metrics {
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), f32>,
+    line_height: f32,
+}
+
+pub struct Font {
+    sprite: Sprite,
+    metrics: Metrics,
+}
+
+thread_local! {
+    static FONT: RefCell<Option<Rc<Font>>> = const { RefCell::new(None) };
+}
+
+// Splits `key=value key="quoted value"` pairs off a metrics line.
+fn fields(line: &str) -> HashMap<&str, &str> {
+    line.split_whitespace()
+        .skip(1)
+        .filter_map(|field| field.split_once('='))
+        .map(|(key, value)| (key, value.trim_matches('"')))
+        .collect()
+}
+
+fn number(fields: &HashMap<&str, &str>, key: &str) -> Result<f32, String> {
+    let value = fields.get(key).ok_or(format!("no {}", key))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid {}: {}", key, value))
+}
+
+fn character(fields: &HashMap<&str, &str>, key: &str) -> Result<char, String> {
+    let value = fields.get(key).ok_or(format!("no {}", key))?;
+    value
+        .parse()
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or(format!("invalid character: {}", value))
+}
+
+impl Font {
+    // Every piece of text shares one font, loaded the first time it's asked
+    // for.
+    pub fn get(display: &Display) -> Result<Rc<Font>, AssetError> {
+        FONT.with(|font| {
+            if let Some(font) = font.borrow().as_ref() {
+                return Ok(font.clone());
+            }
+            let loaded = Rc::new(Font::load(display)?);
+            *font.borrow_mut() = Some(loaded.clone());
+            Ok(loaded)
+        })
+    }
+
+    fn load(display: &Display) -> Result<Font, AssetError> {
+        let sprite = assets::sprite(FONT_IMAGE, display)?;
+        let metrics = Metrics::parse(&assets::text(FONT_METRICS)?)
+            .map_err(|e| AssetError::Malformed(PathBuf::from(FONT_METRICS), e))?;
+        Ok(Font { sprite, metrics })
+    }
+}
+
+impl Metrics {
+    fn parse(text: &str) -> Result<Metrics, String> {
+        let mut metrics = Metrics {
+            glyphs: HashMap::new(),
+            kerning: HashMap::new(),
+            line_height: 0.0,
+        };
+
+        for line in text.lines() {
+            let fields = fields(line);
+            match line.split_whitespace().next() {
+                Some("common") => {
+                    metrics.line_height = number(&fields, "lineHeight")?;
+                }
+                Some("char") => {
+                    let glyph = Glyph {
+                        x: number(&fields, "x")?,
+                        y: number(&fields, "y")?,
+                        width: number(&fields, "width")?,
+                        height: number(&fields, "height")?,
+                        x_offset: number(&fields, "xoffset")?,
+                        y_offset: number(&fields, "yoffset")?,
+                        advance: number(&fields, "xadvance")?,
+                    };
+                    metrics.glyphs.insert(character(&fields, "id")?, glyph);
+                }
+                Some("kerning") => {
+                    let pair = (character(&fields, "first")?, character(&fields, "second")?);
+                    metrics.kerning.insert(pair, number(&fields, "amount")?);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(metrics)
+    }
+
+    fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&FALLBACK))
+    }
+
+    fn kerning(&self, previous: Option<char>, c: char) -> f32 {
+        previous
+            .and_then(|previous| self.kerning.get(&(previous, c)))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    // Width of one line at the font's own size.
+    fn measure(&self, line: &str) -> f32 {
+        let mut width = 0.0;
+        let mut previous = None;
+        for c in line.chars() {
+            if let Some(glyph) = self.glyph(c) {
+                width += self.kerning(previous, c) + glyph.advance;
+            }
+            previous = Some(c);
+        }
+        width
+    }
+
+    // Breaks text into lines no wider than `max_width`, at spaces where it
+    // can. A word too long for a line of its own is left to overflow.
+    fn wrap(&self, text: &str, max_width: Option<f32>) -> Vec<String> {
+        let mut lines = vec![];
+        for paragraph in text.split('\n') {
+            let Some(max_width) = max_width else {
+                lines.push(paragraph.to_string());
+                continue;
+            };
+
+            let mut line = String::new();
+            for word in paragraph.split(' ') {
+                let candidate = if line.is_empty() {
+                    word.to_string()
+                } else {
+                    format!("{} {}", line, word)
+                };
+                if !line.is_empty() && self.measure(&candidate) > max_width {
+                    lines.push(line);
+                    line = word.to_string();
+                } else {
+                    line = candidate;
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+}
+
+// A block of text drawn from the font. It's positioned like a texture, by
+// its centre vertically, while `x` is its left edge, centre or right edge
+// depending on the alignment. Every line is aligned on its own.
+pub struct Text {
+    font: Rc<Font>,
+    text: String,
+    lines: Vec<String>,
+    x: f32,
+    y: f32,
+    align: Align,
+    color: [f32; 4],
+    scale: f32,
+    wrap: Option<f32>,
+    depth: i32,
+}
+
+impl Text {
+    pub fn new(display: &Display, text: &str) -> Result<Self, AssetError> {
+        let font = Font::get(display)?;
+        Ok(Self {
+            lines: font.metrics.wrap(text, None),
+            font,
+            text: text.to_string(),
+            x: 0.0,
+            y: 0.0,
+            align: Align::Left,
+            color: [1.0; 4],
+            scale: 1.0,
+            wrap: None,
+            depth: 0,
+        })
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        if text == self.text {
+            return;
+        }
+        self.text = text.to_string();
+        self.rewrap();
+    }
+
+    fn rewrap(&mut self) {
+        let max_width = self.wrap.map(|width| width / self.scale);
+        self.lines = self.font.metrics.wrap(&self.text, max_width);
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    pub fn set_align(&mut self, align: Align) {
+        self.align = align;
+    }
+
+    // Multiplies the font's colours, which are white with a dark outline.
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+        self.rewrap();
+    }
+
+    // Lines longer than `width` pixels break at spaces; None keeps each
+    // line whole.
+    pub fn set_wrap(&mut self, width: Option<f32>) {
+        self.wrap = width;
+        self.rewrap();
+    }
+
+    pub fn set_depth(&mut self, depth: i32) {
+        self.depth = depth;
+    }
+
+    pub fn height(&self) -> f32 {
+        self.lines.len() as f32 * self.font.metrics.line_height * self.scale
+    }
+
+    pub fn draw(&self, batch: &mut SpriteBatch) {
+        let font = &self.font;
+        let sprite = &font.sprite;
+        let (u_px, v_px) = (sprite.uv[2] / sprite.width, sprite.uv[3] / sprite.height);
+        let scale = self.scale;
+
+        let mut top = self.y + self.height() / 2.0;
+        for line in self.lines.iter() {
+            let width = font.metrics.measure(line) * scale;
+            // whole pixels keep the glyphs sharp
+            let mut pen = match self.align {
+                Align::Left => self.x,
+                Align::Center => self.x - width / 2.0,
+                Align::Right => self.x - width,
+            }
+            .round();
+
+            let mut previous = None;
+            for c in line.chars() {
+                let Some(glyph) = font.metrics.glyph(c) else {
+                    continue;
+                };
+                pen += font.metrics.kerning(previous, c) * scale;
+                previous = Some(c);
+
+                let left = pen + glyph.x_offset * scale;
+                let right = left + glyph.width * scale;
+                let upper = top.round() - glyph.y_offset * scale;
+                let lower = upper - glyph.height * scale;
+                pen += glyph.advance * scale;
+                if glyph.width == 0.0 {
+                    continue;
+                }
+
+                let u0 = sprite.uv[0] + glyph.x * u_px;
+                let v0 = sprite.uv[1] + (sprite.height - glyph.y - glyph.height) * v_px;
+                let (u1, v1) = (u0 + glyph.width * u_px, v0 + glyph.height * v_px);
+
+                let corner = |x: f32, y: f32, u: f32, v: f32| Vertex {
+                    position: [x * 2.0 / SCREEN_WIDTH, y * 2.0 / SCREEN_HEIGHT],
+                    color: self.color,
+                    tex_coords: [u, v],
+                    effect: [0.0, 0.0],
+                };
+                batch.push(
+                    Some(&sprite.page),
+                    self.depth,
+                    [
+                        corner(right, lower, u1, v0),
+                        corner(right, upper, u1, v1),
+                        corner(left, lower, u0, v0),
+                        corner(left, upper, u0, v1),
+                    ],
+                );
+            }
+            top -= font.metrics.line_height * scale;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METRICS: &str = r#"info face="Test" size=16
+common lineHeight=20 base=16 scaleW=64 scaleH=64 pages=1
+page id=0 file="test.png"
+chars count=4
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=5 page=0
+char id=63 x=0 y=0 width=8 height=16 xoffset=0 yoffset=0 xadvance=8 page=0
+char id=97 x=8 y=0 width=10 height=16 xoffset=1 yoffset=2 xadvance=10 page=0
+char id=98 x=18 y=0 width=20 height=16 xoffset=0 yoffset=0 xadvance=20 page=0
+kernings count=1
+kerning first=97 second=98 amount=-2
+"#;
+
+    fn metrics() -> Metrics {
+        Metrics::parse(METRICS).unwrap()
+    }
+
+    #[test]
+    fn parses_glyphs_kerning_and_line_height() {
+        let metrics = metrics();
+        assert_eq!(metrics.line_height, 20.0);
+        assert_eq!(metrics.glyphs.len(), 4);
+
+        let a = &metrics.glyphs[&'a'];
+        assert_eq!((a.x, a.y, a.width, a.height), (8.0, 0.0, 10.0, 16.0));
+        assert_eq!((a.x_offset, a.y_offset, a.advance), (1.0, 2.0, 10.0));
+
+        assert_eq!(metrics.kerning(Some('a'), 'b'), -2.0);
+        assert_eq!(metrics.kerning(Some('b'), 'a'), 0.0);
+        assert_eq!(metrics.kerning(None, 'b'), 0.0);
+    }
+
+    #[test]
+    fn malformed_lines_are_errors() {
+        assert!(Metrics::parse("common base=16").is_err());
+        assert!(Metrics::parse("char id=97 x=0 y=0 width=1 height=1 xoffset=0 yoffset=0").is_err());
+        assert!(Metrics::parse(
+            "char id=a x=0 y=0 width=1 height=1 xoffset=0 yoffset=0 xadvance=1"
+        )
+        .is_err());
+        assert!(Metrics::parse("kerning first=97 second=98 amount=wide").is_err());
+        assert!(Metrics::parse("kerning first=-1 second=98 amount=1").is_err());
+    }
+
+    #[test]
+    fn measures_advances_and_kerning() {
+        let metrics = metrics();
+        assert_eq!(metrics.measure(""), 0.0);
+        assert_eq!(metrics.measure("a"), 10.0);
+        assert_eq!(metrics.measure("ba"), 30.0);
+        assert_eq!(metrics.measure("ab"), 28.0);
+        assert_eq!(metrics.measure("a b"), 35.0);
+    }
+
+    #[test]
+    fn unknown_characters_measure_as_the_fallback() {
+        let metrics = metrics();
+        assert_eq!(metrics.measure("z"), 8.0);
+        assert_eq!(metrics.measure("aza"), 28.0);
+    }
+
+    #[test]
+    fn wraps_at_spaces() {
+        let metrics = metrics();
+        // "aa aa" is 45 wide
+        assert_eq!(metrics.wrap("aa aa", Some(45.0)), ["aa aa"]);
+        assert_eq!(metrics.wrap("aa aa", Some(44.0)), ["aa", "aa"]);
+        assert_eq!(metrics.wrap("a a a a", Some(25.0)), ["a a", "a a"]);
+    }
+
+    #[test]
+    fn long_words_overflow_their_own_line() {
+        let metrics = metrics();
+        assert_eq!(metrics.wrap("a bbbb a", Some(30.0)), ["a", "bbbb", "a"]);
+    }
+
+    #[test]
+    fn newlines_always_break() {
+        let metrics = metrics();
+        assert_eq!(metrics.wrap("a\nb", None), ["a", "b"]);
+        assert_eq!(metrics.wrap("a\n\nb", Some(100.0)), ["a", "", "b"]);
+    }
+
+    #[test]
+    fn no_width_keeps_lines_whole() {
+        let metrics = metrics();
+        assert_eq!(metrics.wrap("a b a b a b a b", None), ["a b a b a b a b"]);
+    }
+
+    // Keeps the shipped font parseable, and able to draw what the game shows.
+    #[test]
+    fn bundled_font_parses() {
+        let metrics = Metrics::parse(&std::fs::read_to_string(FONT_METRICS).unwrap()).unwrap();
+        assert!(metrics.line_height > 0.0);
+        for c in ' '..='~' {
+            assert!(metrics.glyphs.contains_key(&c), "missing {:?}", c);
+        }
+    }
+}
@@ -1,16 +1,8 @@
-use std::io::Cursor;
-use std::{fs::read, vec};
+use glium::Display;
 
-use glium::{uniform, Display, Surface};
-
-use crate::shape::{Direction, Rectangle, RIGHT, TOP};
-
-pub struct Rect {
-    pub x: f32,
-    pub y: f32,
-    pub w: f32,
-    pub h: f32,
-}
+use crate::assets::{self, AssetError, Sprite};
+use crate::batch::SpriteBatch;
+use crate::shape::{Direction, Rectangle, Vertex, RIGHT, TOP};
 
 pub trait Transform {
     fn scale(&mut self, factor: f32);
@@ -18,17 +10,14 @@ pub trait Transform {
     fn set_position(&mut self, x: f32, y: f32);
     fn set_x(&mut self, x: f32);
     fn set_y(&mut self, y: f32);
-    fn mirror(&mut self, display: &Display, dir: Direction);
-    fn get_position(&mut self) -> (f32, f32);
-    fn clip(&mut self, x: f32, y: f32, w: f32, h: f32);
-    fn draw(&self, target: &mut glium::Frame, program: &glium::Program);
-}
-
-pub trait Collide {
-    fn collide_top(&self, other: &Self) -> bool;
-    fn collide_bottom(&self, other: &Self) -> bool;
-    fn collide_left(&self, other: &Self) -> bool;
-    fn collide_right(&self, other: &Self) -> bool;
+    fn mirror(&mut self, dir: Direction);
+    fn set_alpha(&mut self, alpha: f32);
+    fn set_tint(&mut self, tint: [f32; 3]);
+    fn set_flash(&mut self, amount: f32);
+    fn set_hue_shift(&mut self, degrees: f32);
+    fn set_depth(&mut self, depth: i32);
+    fn _clip(&mut self, x: f32, y: f32, w: f32, h: f32);
+    fn draw(&self, batch: &mut SpriteBatch);
 }
 
 pub struct Texture {
@@ -37,53 +26,50 @@ pub struct Texture {
     pub x: f32,
     pub y: f32,
 
-    texture: glium::texture::SrgbTexture2d,
-    clipped: bool,
-    clip_rect: Rect,
+    sprite: Sprite,
+    uv: [f32; 4],
+    alpha: f32,
+    tint: [f32; 3],
+    flash: f32,
+    hue_shift: f32,
     rect: Rectangle,
 }
 
 impl Texture {
-    pub fn new(path: &str, display: &Display) -> Self {
-        let image = image::load(
-            Cursor::new(read(path).expect("Unable to read file")),
-            image::ImageFormat::Png,
-        )
-        .unwrap()
-        .to_rgba8();
-
-        let image_dimensions = image.dimensions();
-        let image =
-            glium::texture::RawImage2d::from_raw_rgba_reversed(&image.into_raw(), image_dimensions);
-
-        let texture = glium::texture::SrgbTexture2d::new(display, image).unwrap();
-
-        let rect = Rectangle::new(
-            display,
-            image_dimensions.0 as f32,
-            image_dimensions.1 as f32,
-        );
+    pub fn new(path: &str, display: &Display) -> Result<Self, AssetError> {
+        Ok(Self::from_sprite(assets::sprite(path, display)?))
+    }
+
+    pub fn from_sprite(sprite: Sprite) -> Self {
+        let rect = Rectangle::new(sprite.width, sprite.height);
 
         Self {
-            width: image_dimensions.0 as f32,
-            height: image_dimensions.1 as f32,
+            width: sprite.width,
+            height: sprite.height,
             x: rect.matrix[3][0] * RIGHT,
             y: rect.matrix[3][1] * TOP,
-            texture: texture,
-            clipped: false,
-            clip_rect: Rect {
-                x: 0.0,
-                y: 0.0,
-                w: 1.0,
-                h: 1.0,
-            },
-            rect: rect,
+            uv: sprite.uv,
+            sprite,
+            alpha: 1.0,
+            tint: [1.0; 3],
+            flash: 0.0,
+            hue_shift: 0.0,
+            rect,
         }
     }
 
     pub fn _get_dimensions(&self) -> (f32, f32) {
         (self.width, self.height)
     }
+
+    // Swaps the image shown without touching position, scale or mirroring.
+    pub fn set_sprite(&mut self, sprite: &Sprite) {
+        self.rect.set_size(sprite.width, sprite.height);
+        self.width = self.rect.width;
+        self.height = self.rect.height;
+        self.uv = sprite.uv;
+        self.sprite = sprite.clone();
+    }
 }
 
 impl Transform for Texture {
@@ -100,10 +86,6 @@ impl Transform for Texture {
         self.y = self.rect.matrix[3][1] * TOP;
     }
 
-    fn get_position(&mut self) -> (f32, f32) {
-        self.rect.get_position()
-    }
-
     fn set_position(&mut self, x: f32, y: f32) {
         self.rect.set_position(x, y);
         self.x = self.rect.matrix[3][0] * RIGHT;
@@ -120,64 +102,58 @@ impl Transform for Texture {
         self.y = self.rect.matrix[3][1] * TOP;
     }
 
-    fn mirror(&mut self, display: &Display, dir: Direction) {
-        self.rect.flip_tex_coords(display, dir);
-    }
-
-    fn clip(&mut self, x: f32, y: f32, w: f32, h: f32) {
-        self.clipped = true;
-        self.clip_rect = Rect {
-            x: x,
-            y: y,
-            w: w,
-            h: h,
-        };
-    }
-
-    fn draw(&self, target: &mut glium::Frame, program: &glium::Program) {
-        let uniforms = uniform! {
-            matrix: self.rect.matrix,
-            isTex: true,
-            tex: &self.texture,
-            clipped: self.clipped,
-            start: [self.clip_rect.x, self.clip_rect.y],
-            size: [self.clip_rect.w, self.clip_rect.h],
-            anim: false,
-        };
-        target
-            .draw(
-                &self.rect.vertex_buffer,
-                &self.rect.index_buffer,
-                program,
-                &uniforms,
-                &glium::DrawParameters {
-                    blend: glium::Blend::alpha_blending(),
-                    ..Default::default()
-                },
-            )
-            .unwrap();
+    fn mirror(&mut self, dir: Direction) {
+        self.rect.flip_tex_coords(dir);
+    }
+
+    fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+
+    // Multiplies the image's colours.
+    fn set_tint(&mut self, tint: [f32; 3]) {
+        self.tint = tint;
     }
-}
 
-impl Collide for Texture {
-    fn collide_bottom(&self, other: &Texture) -> bool {
-        self.y - self.height / 2.0 >= other.y - other.height / 2.0
-            && self.y - self.height / 2.0 <= other.y + other.height / 2.0
-            && (self.x <= other.x + other.width / 2.0 && self.x >= other.x - other.width / 2.0)
+    // Blends the image towards plain white, from 0 (untouched) to 1.
+    fn set_flash(&mut self, amount: f32) {
+        self.flash = amount.clamp(0.0, 1.0);
     }
 
-    fn collide_top(&self, other: &Texture) -> bool {
-        other.collide_bottom(self)
+    fn set_hue_shift(&mut self, degrees: f32) {
+        self.hue_shift = degrees / 360.0;
     }
 
-    fn collide_left(&self, other: &Texture) -> bool {
-        (self.x - self.width / 2.0 >= other.x - other.width / 2.0
-            && self.x - self.width / 2.0 <= other.x + other.width / 2.0)
-            && (self.y <= other.y + other.height / 2.0 && self.y >= other.y - other.height / 2.0)
+    fn set_depth(&mut self, depth: i32) {
+        self.rect.set_depth(depth);
     }
 
-    fn collide_right(&self, other: &Texture) -> bool {
-        other.collide_left(self)
+    // Shows only the given region of the image, in pixels from its top
+    // left corner, centred where the whole image was.
+    fn _clip(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        let sprite = &self.sprite;
+        let (u_px, v_px) = (sprite.uv[2] / sprite.width, sprite.uv[3] / sprite.height);
+        self.uv = [
+            sprite.uv[0] + x * u_px,
+            sprite.uv[1] + (sprite.height - y - h) * v_px,
+            w * u_px,
+            h * v_px,
+        ];
+        self.rect.set_size(w, h);
+        self.width = self.rect.width;
+        self.height = self.rect.height;
+    }
+
+    fn draw(&self, batch: &mut SpriteBatch) {
+        let [u, v, w, h] = self.uv;
+        let [r, g, b] = self.tint;
+        let vertices = self.rect.vertices().map(|vertex| Vertex {
+            color: [r, g, b, self.alpha],
+            tex_coords: [u + vertex.tex_coords[0] * w, v + vertex.tex_coords[1] * h],
+            effect: [self.flash, self.hue_shift],
+            ..vertex
+        });
+        batch.push(Some(&self.sprite.page), self.rect.depth, vertices);
     }
 }
 
@@ -187,13 +163,15 @@ pub enum AnimationMode {
     Once,
 }
 
+// One quad whose image is swapped between frames, each frame being a
+// region of an atlas page.
 pub struct AnimatedTexture {
-    pub width: f32,
     pub height: f32,
     pub x: f32,
     pub y: f32,
 
-    textures: Vec<Texture>,
+    texture: Texture,
+    frames: Vec<Sprite>,
     speed: f32,
     mode: AnimationMode,
     animation_timer: f32,
@@ -203,44 +181,54 @@ pub struct AnimatedTexture {
 }
 
 impl AnimatedTexture {
-    pub fn new(display: &Display, paths: Vec<&str>, speed: f32, frames: usize) -> Self {
-        let mut vec: Vec<Texture> = vec![];
-
-        for i in 0..frames {
-            vec.push(Texture::new(paths[i], display));
-        }
-
-        Self {
-            width: vec[0].width,
-            height: vec[0].height,
-            x: vec[0].x,
-            y: vec[0].y,
-            textures: vec,
-            speed: speed,
+    pub fn new(display: &Display, paths: &[&str], speed: f32) -> Result<Self, AssetError> {
+        let frames = paths
+            .iter()
+            .map(|path| assets::sprite(path, display))
+            .collect::<Result<Vec<Sprite>, AssetError>>()?;
+        let texture = Texture::from_sprite(frames.first().ok_or(AssetError::NoFrames)?.clone());
+
+        Ok(Self {
+            height: texture.height,
+            x: texture.x,
+            y: texture.y,
+            texture,
+            frame_count: frames.len(),
+            frames,
+            speed,
             animation_timer: 0.0,
             mode: AnimationMode::Loop,
             animation_done: false,
             current_frame: 0,
-            frame_count: frames,
-        }
+        })
     }
 
     pub fn set_mode(&mut self, mode: AnimationMode) {
         self.mode = mode;
     }
 
+    fn show_frame(&mut self, frame: u8) {
+        self.current_frame = frame;
+        self.texture.set_sprite(&self.frames[frame as usize]);
+        self.height = self.texture.height;
+    }
+
+    pub fn restart(&mut self) {
+        self.show_frame(0);
+        self.animation_timer = 0.0;
+        self.animation_done = false;
+    }
+
     pub fn run_animation(&mut self, dt: f32) {
         self.animation_timer += dt;
         if self.animation_timer >= self.speed {
+            self.animation_timer -= self.speed;
             if self.mode == AnimationMode::Loop {
-                self.current_frame = (self.current_frame + 1) % self.frame_count as u8;
-                self.animation_timer -= self.speed;
+                self.show_frame((self.current_frame + 1) % self.frame_count as u8);
+            } else if self.current_frame as usize + 1 == self.frame_count {
+                self.animation_done = true;
             } else {
-                self.current_frame += 1;
-                if self.current_frame == self.frame_count as u8 {
-                    self.animation_done = true;
-                    self.current_frame -= 1;
-                }
+                self.show_frame(self.current_frame + 1);
             }
         }
     }
@@ -250,98 +238,65 @@ impl AnimatedTexture {
             self.run_animation(dt);
         }
     }
-
-    pub fn get_dimensions(&self) -> (f32, f32) {
-        (self.width, self.height)
-    }
 }
 
 impl Transform for AnimatedTexture {
     fn scale(&mut self, factor: f32) {
-        for i in 0..self.frame_count {
-            self.textures[i].rect.scale(factor);
-            self.textures[i].height *= factor;
-            self.textures[i].width *= factor;
-        }
+        self.texture.scale(factor);
+        self.height = self.texture.height;
     }
 
     fn translate(&mut self, x: f32, y: f32) {
-        for i in 0..self.frame_count {
-            self.textures[i].translate(x, y);
-        }
-
-        self.x = self.textures[0].x;
-        self.y = self.textures[0].y;
-    }
-
-    fn get_position(&mut self) -> (f32, f32) {
-        self.textures[0].get_position()
+        self.texture.translate(x, y);
+        self.x = self.texture.x;
+        self.y = self.texture.y;
     }
 
     fn set_position(&mut self, x: f32, y: f32) {
-        for i in 0..self.frame_count {
-            self.textures[i].set_position(x, y);
-        }
-
-        self.x = self.textures[0].x;
-        self.y = self.textures[0].y;
+        self.texture.set_position(x, y);
+        self.x = self.texture.x;
+        self.y = self.texture.y;
     }
 
     fn set_x(&mut self, x: f32) {
-        for i in 0..self.frame_count {
-            self.textures[i].set_x(x);
-        }
-        self.x = self.textures[0].x;
+        self.texture.set_x(x);
+        self.x = self.texture.x;
     }
 
     fn set_y(&mut self, y: f32) {
-        for i in 0..self.frame_count {
-            self.textures[i].set_y(y);
-        }
-        self.y = self.textures[0].y;
+        self.texture.set_y(y);
+        self.y = self.texture.y;
     }
 
-    fn mirror(&mut self, display: &Display, dir: Direction) {
-        for i in 0..self.frame_count {
-            self.textures[i].mirror(display, dir);
-        }
+    fn mirror(&mut self, dir: Direction) {
+        self.texture.mirror(dir);
     }
 
-    fn clip(&mut self, x: f32, y: f32, w: f32, h: f32) {
-        for i in 0..self.frame_count {
-            self.textures[i].clipped = true;
-            self.textures[i].clip_rect = Rect {
-                x: x,
-                y: y,
-                w: w,
-                h: h,
-            };
-        }
+    fn set_alpha(&mut self, alpha: f32) {
+        self.texture.set_alpha(alpha);
     }
 
-    fn draw(&self, target: &mut glium::Frame, program: &glium::Program) {
-        self.textures[self.current_frame as usize].draw(target, program);
+    fn set_tint(&mut self, tint: [f32; 3]) {
+        self.texture.set_tint(tint);
     }
-}
 
-impl Collide for AnimatedTexture {
-    fn collide_bottom(&self, other: &AnimatedTexture) -> bool {
-        self.y - self.height / 2.0 >= other.y - other.height / 2.0
-            && self.y - self.height / 2.0 <= other.y + other.height / 2.0
-            && (self.x <= other.x + other.width / 2.0 && self.x >= other.x - other.width / 2.0)
+    fn set_flash(&mut self, amount: f32) {
+        self.texture.set_flash(amount);
     }
 
-    fn collide_top(&self, other: &AnimatedTexture) -> bool {
-        other.collide_bottom(self)
+    fn set_hue_shift(&mut self, degrees: f32) {
+        self.texture.set_hue_shift(degrees);
     }
 
-    fn collide_left(&self, other: &AnimatedTexture) -> bool {
-        (self.x - self.width / 2.0 >= other.x - other.width / 2.0
-            && self.x - self.width / 2.0 <= other.x + other.width / 2.0)
-            && (self.y <= other.y + other.height / 2.0 && self.y >= other.y - other.height / 2.0)
+    fn set_depth(&mut self, depth: i32) {
+        self.texture.set_depth(depth);
     }
 
-    fn collide_right(&self, other: &AnimatedTexture) -> bool {
-        other.collide_left(self)
+    fn _clip(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        self.texture._clip(x, y, w, h);
     }
-}
\ No newline at end of file
+
+    fn draw(&self, batch: &mut SpriteBatch) {
+        self.texture.draw(batch);
+    }
+}